@@ -0,0 +1,160 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use core::fmt;
+use std::{fs, str::FromStr};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::HashsatError;
+
+#[rustfmt::skip]
+pub(crate) const ALPHABET_ALPHANUMERIC: &str ="0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+pub(crate) const ALPHABET_ALPHANUMERIC_UPPERCASE: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub(crate) const ALPHABET_ALPHANUMERIC_LOWERCASE: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+pub(crate) const ALPHABET_UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub(crate) const ALPHABET_LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+pub(crate) const ALPHABET_NUMERIC: &str = "0123456789";
+/// The printable ASCII symbols, including space.
+pub(crate) const ALPHABET_SYMBOLS: &str = " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+pub(crate) const ALPHABET_SPACE: &str = " ";
+
+/// The alphabet candidate passphrases are brute forced from.
+///
+/// An alphabet can be specified in one of three ways:
+/// - `chars:<characters>`: a literal set of characters, e.g. `chars:abc!@# é`.
+/// - `file:<path>`: a file with the characters, ignoring line breaks.
+/// - `<class>+<class>+...`: a composition of named classes, e.g. `lowercase+numeric+symbols`.
+///
+/// BIP39 passphrases are NFKD-normalized before seed derivation, so characters are deduplicated by
+/// their NFKD form. Otherwise, the same passphrase could be tried more than once, e.g. with `Å`
+/// (U+00C5) and the angstrom sign (U+212B). Characters are kept as given, so a base letter and a
+/// combining mark, e.g. `e` and U+0301, stay two characters of the alphabet, and alphabets with a
+/// character that decomposes into other characters of theirs, e.g. `é` (U+00E9) along with them,
+/// are rejected, as `é` and `e` followed by U+0301 are the same passphrase.
+#[derive(Clone, Debug)]
+pub struct Alphabet {
+    /// The alphabet, as given by the user.
+    spec: String,
+    /// The characters of the alphabet.
+    chars: Vec<char>,
+}
+
+impl Alphabet {
+    /// The characters of the alphabet.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = HashsatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| HashsatError::InvalidAlphabet(s.to_string(), reason);
+
+        let raw = if let Some(literal) = s.strip_prefix("chars:") {
+            literal.to_string()
+        } else if let Some(path) = s.strip_prefix("file:") {
+            fs::read_to_string(path)?.replace(['\r', '\n'], "")
+        } else {
+            s.split('+')
+                .map(|class| {
+                    get_class(class).ok_or_else(|| invalid(format!("unknown class `{class}`")))
+                })
+                .collect::<Result<String, _>>()?
+        };
+
+        let mut chars: Vec<char> = Vec::new();
+        let mut normalized: Vec<String> = Vec::new();
+        for c in raw.chars() {
+            let nfkd: String = c.nfkd().collect();
+            if !normalized.contains(&nfkd) {
+                chars.push(c);
+                normalized.push(nfkd);
+            }
+        }
+        if chars.is_empty() {
+            return Err(invalid("empty alphabet".to_string()));
+        }
+        // A character that decomposes into characters of the alphabet is spelled by them too.
+        for (c, nfkd) in chars.iter().zip(&normalized) {
+            if nfkd.chars().count() > 1
+                && nfkd
+                    .chars()
+                    .all(|part| normalized.contains(&part.to_string()))
+            {
+                return Err(invalid(format!(
+                    "`{c}` is also spelled by the characters `{}` of the alphabet",
+                    nfkd.escape_unicode()
+                )));
+            }
+        }
+
+        Ok(Self {
+            spec: s.to_string(),
+            chars,
+        })
+    }
+}
+
+impl Default for Alphabet {
+    /// The `alphanumeric` alphabet.
+    fn default() -> Self {
+        Self {
+            spec: "alphanumeric".to_string(),
+            chars: ALPHABET_ALPHANUMERIC.chars().collect(),
+        }
+    }
+}
+
+impl fmt::Display for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+/// Get the characters of a named class from its identifier.
+fn get_class(identifier: &str) -> Option<&'static str> {
+    match identifier {
+        "alphanumeric" => Some(ALPHABET_ALPHANUMERIC),
+        "alphanumeric_uppercase" => Some(ALPHABET_ALPHANUMERIC_UPPERCASE),
+        "alphanumeric_lowercase" => Some(ALPHABET_ALPHANUMERIC_LOWERCASE),
+        "uppercase" | "upper" => Some(ALPHABET_UPPERCASE),
+        "lowercase" | "lower" => Some(ALPHABET_LOWERCASE),
+        "numeric" | "digits" => Some(ALPHABET_NUMERIC),
+        "symbols" => Some(ALPHABET_SYMBOLS),
+        "space" => Some(ALPHABET_SPACE),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_alphabets() {
+        let alphabet = Alphabet::from_str("numeric+symbols+numeric").unwrap();
+        assert_eq!(alphabet.chars().len(), 10 + 33);
+
+        // Characters with the same NFKD form are the same passphrase character.
+        let alphabet = Alphabet::from_str("chars:\u{c5}\u{212b}K\u{212a}!").unwrap();
+        assert_eq!(alphabet.chars(), ['\u{c5}', 'K', '!']);
+
+        // Combining marks are not merged into the characters before them, but precomposed
+        // characters they would spell with a base letter of the alphabet are rejected.
+        let alphabet = Alphabet::from_str("chars:e\u{301}").unwrap();
+        assert_eq!(alphabet.chars(), ['e', '\u{301}']);
+        assert!(Alphabet::from_str("chars:\u{e9}e").is_ok());
+        assert!(Alphabet::from_str("chars:\u{e9}\u{301}").is_ok());
+        assert!(matches!(
+            Alphabet::from_str("chars:\u{e9}e\u{301}"),
+            Err(HashsatError::InvalidAlphabet(..))
+        ));
+        // So are compatibility characters, like the `ﬁ` ligature (U+FB01).
+        assert!(Alphabet::from_str("chars:fi\u{fb01}").is_err());
+
+        assert!(Alphabet::from_str("lowercase+emoji").is_err());
+        assert!(Alphabet::from_str("chars:").is_err());
+    }
+}
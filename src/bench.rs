@@ -0,0 +1,312 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{
+    collections::HashSet,
+    hint::black_box,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bip39::Mnemonic;
+use bitcoin::{
+    Network, NetworkKind,
+    bip32::{ChildNumber, Xpriv, Xpub},
+    key::Secp256k1,
+    secp256k1::All,
+};
+use rayon::ThreadPoolBuilder;
+
+use crate::{
+    cracker::derive_wallet_and_assert_with,
+    descriptor::{Descriptor, Payload, ScriptType},
+    error::HashsatError,
+    matcher::AddressMatcher,
+    pbkdf2::{Backend, SeedDeriver},
+};
+
+/// The mnemonic wallets are derived from. Its words don't affect the rates.
+const BENCH_MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+/// The keychains searched on each wallet, as when cracking by default.
+const BENCH_KEYCHAINS: [u32; 2] = [0, 1];
+/// The search width on each keychain, as when cracking by default.
+const BENCH_SEARCH_WIDTH: u32 = 10;
+
+/// The script types, in the order they are benchmarked.
+const SCRIPT_TYPES: [ScriptType; 4] = [
+    ScriptType::Pkh,
+    ScriptType::ShWpkh,
+    ScriptType::Wpkh,
+    ScriptType::Tr,
+];
+
+/// A measured stage of testing a candidate passphrase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Seed derivation from the mnemonic and passphrase.
+    Pbkdf2,
+    /// Master key creation from the seed.
+    MasterKey,
+    /// Account and child key derivation from the master key.
+    ChildDerivation,
+    /// Matching the child keys of an address type against the targets.
+    AddressMatching(ScriptType),
+    /// All of the above, for an address type.
+    Total(ScriptType),
+}
+
+impl Stage {
+    /// All stages, in the order they are benchmarked.
+    pub fn all() -> Vec<Stage> {
+        let mut stages = vec![Stage::Pbkdf2, Stage::MasterKey, Stage::ChildDerivation];
+        stages.extend(SCRIPT_TYPES.map(Stage::AddressMatching));
+        stages.extend(SCRIPT_TYPES.map(Stage::Total));
+        stages
+    }
+
+    /// The name of the stage, without the address type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Pbkdf2 => "pbkdf2",
+            Stage::MasterKey => "master key",
+            Stage::ChildDerivation => "child derivation",
+            Stage::AddressMatching(_) => "address matching",
+            Stage::Total(_) => "total",
+        }
+    }
+
+    /// The address type the stage is specific to, if any.
+    pub fn script_type(&self) -> Option<ScriptType> {
+        match self {
+            Stage::AddressMatching(script_type) | Stage::Total(script_type) => Some(*script_type),
+            _ => None,
+        }
+    }
+}
+
+/// The rate of a stage, on a number of threads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    /// The measured stage.
+    pub stage: Stage,
+    /// How many threads the stage ran on.
+    pub threads: usize,
+    /// How many wallets per second went through the stage.
+    pub rate: f64,
+}
+
+/// Benchmark every stage of testing a candidate passphrase with `backend`, for each address type
+/// and thread count, and return their rates in wallets per second. Each measurement is passed to
+/// `on_measurement` as soon as it is taken, stage by stage and thread count by thread count.
+///
+/// Each stage is measured on its own, over the work a single wallet takes when cracking with the
+/// default keychains and search width, so rates can be compared across machines and versions.
+pub fn bench(
+    backend: Backend,
+    threads: &[usize],
+    duration: Duration,
+    mut on_measurement: impl FnMut(&Measurement),
+) -> Result<Vec<Measurement>, HashsatError> {
+    let mnemonic = Mnemonic::from_str(BENCH_MNEMONIC)?;
+    let seed_deriver = SeedDeriver::with_backend(&mnemonic, backend);
+
+    // The inputs of each stage are the outputs of the previous one.
+    let passphrases = vec!["hashsat".to_string(); seed_deriver.lanes()];
+    let seed = mnemonic.to_seed_normalized(&passphrases[0]);
+    let secp = Secp256k1::new();
+    let master_xpriv = Xpriv::new_master(NetworkKind::Main, &seed).unwrap();
+    let descriptors: Vec<Descriptor> = SCRIPT_TYPES
+        .iter()
+        .map(|script_type| {
+            Descriptor::new(
+                *script_type,
+                script_type.standard_account_path(0),
+                BENCH_KEYCHAINS.to_vec(),
+            )
+        })
+        .collect();
+    let descriptor_of = |script_type| {
+        descriptors
+            .iter()
+            .find(|d| d.script_type == script_type)
+            .unwrap()
+            .clone()
+    };
+    let child_xpubs = derive_children(&secp, &master_xpriv, &descriptors[0]);
+    // Targets that are never matched, so every child key is tested.
+    let target_payloads: HashSet<Payload> = HashSet::from([
+        Payload::PubkeyHash([0; 20]),
+        Payload::ScriptHash([0; 20]),
+        Payload::WPubkeyHash([0; 20]),
+        Payload::OutputKey([0; 32]),
+    ]);
+
+    let mut measurements = Vec::new();
+    for stage in Stage::all() {
+        for &threads in threads {
+            let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+            let rate = pool.install(|| match stage {
+                Stage::Pbkdf2 => measure(duration, |_| {
+                    black_box(seed_deriver.seeds(&passphrases));
+                    passphrases.len()
+                }),
+                Stage::MasterKey => measure(duration, |_| {
+                    black_box(Xpriv::new_master(NetworkKind::Main, black_box(&seed)).unwrap());
+                    1
+                }),
+                Stage::ChildDerivation => measure(duration, |secp| {
+                    black_box(derive_children(secp, &master_xpriv, &descriptors[0]));
+                    1
+                }),
+                Stage::AddressMatching(script_type) => {
+                    let descriptor = descriptor_of(script_type);
+                    measure(duration, |secp| {
+                        for xpub in &child_xpubs {
+                            black_box(target_payloads.contains(&descriptor.payload(secp, xpub)));
+                        }
+                        1
+                    })
+                }
+                Stage::Total(script_type) => {
+                    let matcher = AddressMatcher {
+                        payloads: target_payloads.clone(),
+                        descriptors: vec![descriptor_of(script_type)],
+                        search_width: BENCH_SEARCH_WIDTH,
+                        network: Network::Bitcoin,
+                    };
+                    measure(duration, |secp| {
+                        let seeds = seed_deriver.seeds(&passphrases);
+                        for (passphrase, seed) in passphrases.iter().zip(&seeds) {
+                            black_box(derive_wallet_and_assert_with(
+                                secp,
+                                &matcher,
+                                Network::Bitcoin,
+                                passphrase,
+                                seed,
+                            ));
+                        }
+                        passphrases.len()
+                    })
+                }
+            });
+            let measurement = Measurement {
+                stage,
+                threads,
+                rate,
+            };
+            on_measurement(&measurement);
+            measurements.push(measurement);
+        }
+    }
+
+    Ok(measurements)
+}
+
+/// Run `work` on every thread of the current thread pool for `duration`, and return how many
+/// wallets per second went through it. `work` returns how many wallets it processed.
+pub(crate) fn measure(duration: Duration, work: impl Fn(&Secp256k1<All>) -> usize + Sync) -> f64 {
+    let wallets = AtomicUsize::new(0);
+    let start = Instant::now();
+    rayon::broadcast(|_| {
+        let secp = Secp256k1::new();
+        let mut local = 0;
+        while start.elapsed() < duration {
+            local += work(&secp);
+        }
+        wallets.fetch_add(local, Ordering::Relaxed);
+    });
+
+    wallets.load(Ordering::Relaxed) as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Derive the child keys of a descriptor that are searched on each wallet.
+fn derive_children(
+    secp: &Secp256k1<All>,
+    master_xpriv: &Xpriv,
+    descriptor: &Descriptor,
+) -> Vec<Xpub> {
+    let account_xpub = Xpub::from_priv(
+        secp,
+        &master_xpriv
+            .derive_priv(secp, &descriptor.account_path)
+            .unwrap(),
+    );
+
+    let mut children = Vec::new();
+    for &keychain in &descriptor.keychains {
+        let keychain_xpub = account_xpub
+            .derive_pub(secp, &[ChildNumber::Normal { index: keychain }])
+            .unwrap();
+        for index in 0..BENCH_SEARCH_WIDTH {
+            children.push(
+                keychain_xpub
+                    .derive_pub(secp, &[ChildNumber::Normal { index }])
+                    .unwrap(),
+            );
+        }
+    }
+    children
+}
+
+/// The thread counts benchmarked by default: powers of two up to the available parallelism, and
+/// the available parallelism itself.
+pub fn default_bench_threads() -> Vec<usize> {
+    let max = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut threads: Vec<usize> = (0..).map(|exp| 1 << exp).take_while(|&n| n < max).collect();
+    threads.push(max);
+    threads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages() {
+        let stages = Stage::all();
+        assert_eq!(stages.len(), 3 + 2 * SCRIPT_TYPES.len());
+        assert_eq!(
+            stages[..4],
+            [
+                Stage::Pbkdf2,
+                Stage::MasterKey,
+                Stage::ChildDerivation,
+                Stage::AddressMatching(ScriptType::Pkh)
+            ]
+        );
+        assert_eq!(stages.last(), Some(&Stage::Total(ScriptType::Tr)));
+
+        assert_eq!(Stage::ChildDerivation.name(), "child derivation");
+        assert_eq!(Stage::ChildDerivation.script_type(), None);
+        assert_eq!(
+            Stage::AddressMatching(ScriptType::Wpkh).name(),
+            "address matching"
+        );
+        assert_eq!(
+            Stage::Total(ScriptType::ShWpkh).script_type(),
+            Some(ScriptType::ShWpkh)
+        );
+    }
+
+    #[test]
+    fn measurements() {
+        let mut measured = Vec::new();
+        let measurements = bench(Backend::Scalar, &[1, 2], Duration::from_millis(1), |m| {
+            measured.push(*m)
+        })
+        .unwrap();
+        assert_eq!(measurements, measured);
+
+        // Every stage, on every thread count, in order.
+        let order: Vec<(Stage, usize)> =
+            measurements.iter().map(|m| (m.stage, m.threads)).collect();
+        let expected: Vec<(Stage, usize)> = Stage::all()
+            .into_iter()
+            .flat_map(|stage| [(stage, 1), (stage, 2)])
+            .collect();
+        assert_eq!(order, expected);
+        assert!(measurements.iter().all(|m| m.rate > 0.0));
+    }
+}
@@ -0,0 +1,132 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::source::CandidateSource;
+
+/// Round-Robin iteration between different length passphrases.
+pub(crate) struct RoundRobinIter {
+    /// Scrambled alphabet for each passphrase subset.
+    alphabets: Vec<Vec<char>>,
+    /// Passphrase size of each subset.
+    sizes: Vec<usize>,
+    /// Number of passphrases in each subset.
+    totals: Vec<u128>,
+    /// Position of the next passphrase in each subset.
+    positions: Vec<u128>,
+    /// Current position in the Round-Robin cycle.
+    current_idx: usize,
+}
+
+impl RoundRobinIter {
+    pub(crate) fn new(min: usize, max: usize, alphabet: String, seed: u64) -> Self {
+        // Scramble the alphabet on every run so walks across
+        // the search space are random instead of lexicographical.
+        // This make sure that different runs walk different paths,
+        // while the same seed always walks the same path.
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let (mut alphabets, mut sizes, mut totals) = (Vec::new(), Vec::new(), Vec::new());
+        for size in min..=max {
+            let mut chars: Vec<char> = alphabet.chars().collect();
+            chars.shuffle(&mut rng);
+
+            totals.push(
+                (chars.len() as u128)
+                    .checked_pow(size as u32)
+                    .unwrap_or(u128::MAX),
+            );
+            alphabets.push(chars);
+            sizes.push(size);
+        }
+        let n_subsets = sizes.len();
+
+        Self {
+            alphabets,
+            sizes,
+            totals,
+            positions: vec![0; n_subsets],
+            current_idx: 0,
+        }
+    }
+}
+
+impl Iterator for RoundRobinIter {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n_subsets = self.sizes.len();
+        for _ in 0..n_subsets {
+            let idx = self.current_idx;
+            self.current_idx = (self.current_idx + 1) % n_subsets;
+
+            if self.positions[idx] < self.totals[idx] {
+                let passphrase =
+                    passphrase_at(self.positions[idx], self.sizes[idx], &self.alphabets[idx]);
+                self.positions[idx] += 1;
+                return Some(passphrase);
+            }
+        }
+
+        None
+    }
+}
+
+impl CandidateSource for RoundRobinIter {
+    fn keyspace(&self) -> Option<u128> {
+        Some(
+            self.totals
+                .iter()
+                .fold(0u128, |acc, total| acc.saturating_add(*total)),
+        )
+    }
+
+    fn position(&self) -> Vec<u128> {
+        let mut position = vec![self.current_idx as u128];
+        position.extend(&self.positions);
+        position
+    }
+
+    fn seek(&mut self, position: &[u128]) -> bool {
+        match position.split_first() {
+            Some((&current_idx, positions)) if positions.len() == self.positions.len() => {
+                self.current_idx = current_idx as usize % self.sizes.len().max(1);
+                self.positions.copy_from_slice(positions);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[allow(dead_code)]
+/// Generate all candidate passphrases up to size `size` using the `Radix Conversion` algorithm.
+///
+/// Rust iterators are lazy (they're only evaluated when used),
+/// so we are not allocating a shit ton of memory with all passphrase combinations.
+fn generate_passphrases_up_to(size: usize, alphabet: String) -> impl Iterator<Item = String> {
+    (1..=size).flat_map(move |length| {
+        let chars: Vec<char> = alphabet.chars().collect();
+        (0..(chars.len() as u128).pow(length as u32)).map(move |mut n| {
+            let mut result = String::new();
+            for _ in 0..length {
+                result.push(chars[n as usize % chars.len()]);
+                n /= chars.len() as u128;
+            }
+            result
+        })
+    })
+}
+
+/// Get the `n`-th candidate passphrase of size `size` using the `Radix Conversion` algorithm.
+///
+/// Since any passphrase can be computed from its index alone, we are not allocating a shit ton
+/// of memory with all passphrase combinations, and can resume from any position.
+fn passphrase_at(mut n: u128, size: usize, chars: &[char]) -> String {
+    let mut result = String::new();
+    for _ in 0..size {
+        result.push(chars[(n % chars.len() as u128) as usize]);
+        n /= chars.len() as u128;
+    }
+    result
+}
@@ -0,0 +1,414 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use bip39::Mnemonic;
+use bitcoin::{
+    Address, Network, NetworkKind,
+    address::NetworkUnchecked,
+    bip32::{self, DerivationPath, Fingerprint, Xpub},
+};
+
+use crate::{
+    alphabet::Alphabet,
+    cracker::Cracker,
+    descriptor::{Descriptor, ScriptType},
+    error::HashsatError,
+    mask::Mask,
+    matcher::{CustomMatcher, Matcher},
+    source::{CandidateSource, Generator},
+    types::Wallet,
+};
+
+/// The first hardened child index.
+const HARDENED: u32 = 1 << 31;
+
+/// A builder for a [`Cracker`], which validates its configuration and fills in the defaults.
+///
+/// At least one target must be set: an address, an account xpub, a master key fingerprint or a
+/// custom matcher.
+/// Candidates are taken from the custom source if set, read from the wordlist if set, generated
+/// from the mask if set, or brute forced from the alphabet otherwise.
+#[derive(Clone, Debug)]
+pub struct CrackerBuilder {
+    mnemonic: Mnemonic,
+    alphabet: Alphabet,
+    wordlist: Option<PathBuf>,
+    mask: Option<Mask>,
+    generator: Option<Generator>,
+    rules: Option<PathBuf>,
+    network: Network,
+    target_addresses: Vec<Address<NetworkUnchecked>>,
+    target_xpub: Option<(Xpub, Option<ScriptType>)>,
+    target_fingerprint: Option<Fingerprint>,
+    custom_matcher: Option<CustomMatcher>,
+    descriptors: Vec<Descriptor>,
+    derivation_path: Option<DerivationPath>,
+    coin_type: Option<u32>,
+    keychains: Vec<u32>,
+    search_width: usize,
+    passphrase_length_range: (usize, usize),
+    part: Option<(usize, usize)>,
+    seed: Option<u64>,
+    session: Option<PathBuf>,
+    restore: bool,
+    overwrite_session: bool,
+    checkpoint_interval: Duration,
+}
+
+impl CrackerBuilder {
+    /// Start configuring a cracker for the passphrase of `mnemonic`, brute forcing alphanumeric
+    /// passphrases of 1 to 10 characters on bitcoin by default.
+    pub fn new(mnemonic: Mnemonic) -> Self {
+        Self {
+            mnemonic,
+            alphabet: Alphabet::default(),
+            wordlist: None,
+            mask: None,
+            generator: None,
+            rules: None,
+            network: Network::Bitcoin,
+            target_addresses: Vec::new(),
+            target_xpub: None,
+            target_fingerprint: None,
+            custom_matcher: None,
+            descriptors: Vec::new(),
+            derivation_path: None,
+            coin_type: None,
+            keychains: vec![0, 1],
+            search_width: 10,
+            passphrase_length_range: (1, 10),
+            part: None,
+            seed: None,
+            session: None,
+            restore: false,
+            overwrite_session: false,
+            checkpoint_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// The alphabet to brute force passphrases from.
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// A wordlist file to read candidate passphrases from, one per line. A path of `-` reads from
+    /// stdin.
+    pub fn wordlist(mut self, wordlist: impl Into<PathBuf>) -> Self {
+        self.wordlist = Some(wordlist.into());
+        self
+    }
+
+    /// A mask to generate candidate passphrases from.
+    pub fn mask(mut self, mask: Mask) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// A custom source of candidate passphrases, used instead of the alphabet, wordlist or mask.
+    /// `source` is called to create a fresh source for every run, and for the estimate.
+    pub fn candidates<S: CandidateSource + 'static>(
+        mut self,
+        source: impl Fn() -> S + Send + Sync + 'static,
+    ) -> Self {
+        self.generator = Some(Generator::new(source));
+        self
+    }
+
+    /// A hashcat-compatible rule file to mangle every candidate passphrase with.
+    pub fn rules(mut self, rules: impl Into<PathBuf>) -> Self {
+        self.rules = Some(rules.into());
+        self
+    }
+
+    /// The network to search for addresses at.
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Add a known address of the wallet. Can be called many times, with addresses of different
+    /// types.
+    pub fn target_address(mut self, address: Address<NetworkUnchecked>) -> Self {
+        self.target_addresses.push(address);
+        self
+    }
+
+    /// A known account extended public key of the wallet, matched instead of addresses, with the
+    /// script type of its account, if known. Otherwise, the standard derivation paths of every
+    /// script type are searched.
+    pub fn target_xpub(mut self, xpub: Xpub, script_type: impl Into<Option<ScriptType>>) -> Self {
+        self.target_xpub = Some((xpub, script_type.into()));
+        self
+    }
+
+    /// The known master key fingerprint of the wallet.
+    pub fn target_fingerprint(mut self, fingerprint: Fingerprint) -> Self {
+        self.target_fingerprint = Some(fingerprint);
+        self
+    }
+
+    /// A custom matcher, which must match on top of the targets, if any. Combine matchers with
+    /// [`All`](crate::matcher::All) and [`Any`](crate::matcher::Any) for other logic.
+    pub fn matcher(mut self, matcher: impl Matcher + 'static) -> Self {
+        self.custom_matcher = Some(CustomMatcher(Arc::new(matcher)));
+        self
+    }
+
+    /// Add a descriptor template of the wallet. Can be called many times. When set, the
+    /// derivation path, coin type and keychains are ignored, and target addresses must be of the
+    /// script type of one of the descriptors.
+    pub fn descriptor(mut self, descriptor: Descriptor) -> Self {
+        if !self.descriptors.contains(&descriptor) {
+            self.descriptors.push(descriptor);
+        }
+        self
+    }
+
+    /// A non-standard account derivation path of the wallet.
+    pub fn derivation_path(mut self, derivation_path: DerivationPath) -> Self {
+        self.derivation_path = Some(derivation_path);
+        self
+    }
+
+    /// The BIP44 coin type used on the standard derivation path. Defaults to `0` on bitcoin and
+    /// `1` on test networks. Coin types are hardened, so they must be below `2^31`.
+    pub fn coin_type(mut self, coin_type: u32) -> Self {
+        self.coin_type = Some(coin_type);
+        self
+    }
+
+    /// The keychains to derive addresses from. Defaults to both the external and the internal
+    /// keychains. Keychains can't be hardened, i.e. `2^31` or above.
+    pub fn keychains(mut self, keychains: impl IntoIterator<Item = u32>) -> Self {
+        self.keychains = keychains.into_iter().collect();
+        self
+    }
+
+    /// How many addresses to derive on each keychain, at most `2^31`, as addresses are derived at
+    /// normal indices.
+    pub fn search_width(mut self, search_width: usize) -> Self {
+        self.search_width = search_width;
+        self
+    }
+
+    /// The range of passphrase lengths to brute force, where `min <= max`. A `min` of `0` includes
+    /// the empty passphrase.
+    pub fn passphrase_length_range(mut self, min: usize, max: usize) -> Self {
+        self.passphrase_length_range = (min, max);
+        self
+    }
+
+    /// Only search part `k` out of `n` of the candidate space.
+    pub fn part(mut self, k: usize, n: usize) -> Self {
+        self.part = Some((k, n));
+        self
+    }
+
+    /// The seed used to scramble the alphabet. Defaults to a random seed, or to `0` when searching
+    /// a part of the candidate space.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// A session file to periodically checkpoint progress to.
+    pub fn session(mut self, session: impl Into<PathBuf>) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    /// Whether to restore progress from the session file.
+    pub fn restore(mut self, restore: bool) -> Self {
+        self.restore = restore;
+        self
+    }
+
+    /// Whether to overwrite the session file if it already exists and isn't restored. Otherwise,
+    /// cracking refuses to start, so the progress it holds isn't lost.
+    pub fn overwrite_session(mut self, overwrite_session: bool) -> Self {
+        self.overwrite_session = overwrite_session;
+        self
+    }
+
+    /// How often progress is checkpointed to the session file.
+    pub fn checkpoint_interval(mut self, checkpoint_interval: Duration) -> Self {
+        self.checkpoint_interval = checkpoint_interval;
+        self
+    }
+
+    /// Validate the configuration and build the [`Cracker`].
+    pub fn build(self) -> Result<Cracker, HashsatError> {
+        let network = self.network;
+
+        // Check the target addresses against the network, deduplicated.
+        let mut target_addresses: Vec<Address> = Vec::new();
+        for target_address in self.target_addresses {
+            let target_address = target_address.require_network(network)?;
+            if !target_addresses.contains(&target_address) {
+                target_addresses.push(target_address);
+            }
+        }
+        if let Some((xpub, _)) = &self.target_xpub
+            && xpub.network != NetworkKind::from(network)
+        {
+            return Err(HashsatError::InvalidXpub(
+                xpub.to_string(),
+                format!("not a {network} extended public key"),
+            ));
+        }
+        let (min, max) = self.passphrase_length_range;
+        if min > max {
+            return Err(HashsatError::InvalidLengthRange(min, max));
+        }
+        if let Some((k, n)) = self.part
+            && (k == 0 || k > n)
+        {
+            return Err(HashsatError::InvalidPart(k, n));
+        }
+        if let Some(coin_type) = self.coin_type
+            && coin_type >= HARDENED
+        {
+            return Err(HashsatError::InvalidCoinType(coin_type));
+        }
+        // Addresses are derived with normal derivation below the account.
+        if let Some(&keychain) = self.keychains.iter().find(|&&k| k >= HARDENED) {
+            return Err(HashsatError::InvalidKeychain(keychain));
+        }
+        if self.search_width > HARDENED as usize {
+            return Err(HashsatError::InvalidSearchWidth(self.search_width));
+        }
+
+        // Use the standard descriptor for each of the target address types, on the given
+        // derivation path and keychains, if no descriptor templates are provided.
+        let mut descriptors = self.descriptors;
+        if descriptors.is_empty() {
+            let script_types = match &self.target_xpub {
+                Some((_, Some(script_type))) => vec![*script_type],
+                Some((_, None)) => ScriptType::ALL.to_vec(),
+                None => target_addresses
+                    .iter()
+                    .map(|address| {
+                        ScriptType::try_from(address.address_type().ok_or(
+                            HashsatError::InvalidDerivationPath(
+                                bip32::Error::InvalidDerivationPathFormat,
+                            ),
+                        )?)
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+            // Get the keychains, deduplicated.
+            let mut keychains = self.keychains;
+            keychains.sort_unstable();
+            keychains.dedup();
+            for script_type in script_types {
+                if descriptors.iter().any(|d| d.script_type == script_type) {
+                    continue;
+                }
+                let account_path = match &self.derivation_path {
+                    Some(derivation_path) => derivation_path.clone(),
+                    None => script_type.standard_account_path(
+                        self.coin_type.unwrap_or_else(|| coin_type(network)),
+                    ),
+                };
+                descriptors.push(Descriptor::new(
+                    script_type,
+                    account_path,
+                    keychains.clone(),
+                ));
+            }
+        }
+
+        // Every target address must be of a script type derived by the descriptors, or it can never
+        // be matched.
+        for address in &target_addresses {
+            let address_type = address
+                .address_type()
+                .ok_or_else(|| HashsatError::UnsupportedAddressType(address.to_string()))?;
+            let script_type = ScriptType::try_from(address_type)?;
+            if !descriptors.iter().any(|d| d.script_type == script_type) {
+                return Err(HashsatError::UnsupportedAddressType(format!(
+                    "{address_type} of target address {address}, as no descriptor derives it"
+                )));
+            }
+        }
+
+        // The origin fingerprints of the descriptors are target fingerprints too, so they must agree.
+        let mut target_fingerprint = self.target_fingerprint;
+        for fingerprint in descriptors.iter().filter_map(|d| d.fingerprint) {
+            match target_fingerprint {
+                Some(target_fingerprint) if target_fingerprint != fingerprint => {
+                    return Err(HashsatError::InvalidFingerprint(format!(
+                        "conflicting fingerprints {target_fingerprint} and {fingerprint}"
+                    )));
+                }
+                _ => target_fingerprint = Some(fingerprint),
+            }
+        }
+        if target_addresses.is_empty()
+            && self.target_xpub.is_none()
+            && target_fingerprint.is_none()
+            && self.custom_matcher.is_none()
+        {
+            return Err(HashsatError::MissingTarget);
+        }
+
+        // The target extended public key must be at the depth of the account derivation path.
+        let target_xpub = match self.target_xpub {
+            Some((xpub, _))
+                if let Some(descriptor) = descriptors
+                    .iter()
+                    .find(|d| d.account_path.len() != xpub.depth as usize) =>
+            {
+                return Err(HashsatError::InvalidXpub(
+                    xpub.to_string(),
+                    format!(
+                        "depth {} doesn't match derivation path {}, use the `--derivation-path` or `--descriptor` flags",
+                        xpub.depth, descriptor.account_path
+                    ),
+                ));
+            }
+            Some((xpub, _)) => Some(xpub),
+            None => None,
+        };
+
+        // All parts must walk the same path over the candidate space, so the seed can't be random.
+        let seed = self.seed.or(self.part.map(|_| 0));
+
+        let wallet = Wallet {
+            mnemonic: self.mnemonic,
+            alphabet: self.alphabet,
+            wordlist: self.wordlist,
+            mask: self.mask,
+            generator: self.generator,
+            rules: self.rules,
+            target_addresses,
+            target_xpub,
+            target_fingerprint,
+            custom_matcher: self.custom_matcher,
+            descriptors,
+            search_width: self.search_width,
+            passphrase_length_range: self.passphrase_length_range,
+            network,
+            part: self.part,
+            seed,
+            session: self.session,
+            restore: self.restore,
+            overwrite_session: self.overwrite_session,
+            checkpoint_interval: self.checkpoint_interval,
+        };
+        // Check that the targets can be matched against.
+        wallet.matcher()?;
+
+        Ok(Cracker::new(wallet))
+    }
+}
+
+/// Get the BIP44 coin type for a [`Network`]: `0` for bitcoin and `1` for all test networks.
+fn coin_type(network: Network) -> u32 {
+    match network {
+        Network::Bitcoin => 0,
+        _ => 1,
+    }
+}
@@ -1,37 +1,110 @@
 //! hashsat: a bitcoin passphrase cracker
 
-use std::str::FromStr;
+use std::{fs, path::PathBuf, str::FromStr, time::Duration};
 
-use bip39::Mnemonic;
-use bitcoin::{
-    Address, AddressType, Network,
-    bip32::{self, DerivationPath},
+use clap::{ArgGroup, Args, Parser, Subcommand, builder::PossibleValuesParser};
+use hashsat::{
+    Alphabet, Cracker, Descriptor, HashsatError, Mask,
+    bip39::Mnemonic,
+    bitcoin::{
+        Address, Network,
+        bip32::{DerivationPath, Fingerprint},
+    },
+    decode_xpub,
 };
-use clap::{Parser, builder::PossibleValuesParser};
-
-use crate::{error::HashsatError, types::Wallet};
 
 #[derive(Parser, Debug)]
-#[command(version, name = "hashsat", about = "a bitcoin passphrase cracker")]
+#[command(
+    version,
+    name = "hashsat",
+    about = "a bitcoin passphrase cracker",
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true,
+    group = ArgGroup::new("words").args(["wordlist", "mask"])
+)]
 pub(crate) struct Arguments {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+
     #[arg(
         short,
         long,
         value_name = "mnemonic",
+        required = true,
         help = "12, 15, 18, 21 or 24 word mnemonic"
     )]
-    pub(crate) mnemonic: String,
+    pub(crate) mnemonic: Option<String>,
 
     #[arg(
         short,
         long,
         value_name = "alphabet",
         default_value = "alphanumeric",
-        value_parser = PossibleValuesParser::new(["alphanumeric", "alphanumeric_uppercase", "alphanumeric_lowercase", "uppercase", "lowercase", "numeric"]),
-        help = "The alphabet to search passphrases from. Constraining the passphrase search space will improve cracking times exponentially"
+        help = "The alphabet to search passphrases from. Constraining the passphrase search space will improve cracking times exponentially. Either named classes joined by `+` (alphanumeric, alphanumeric_uppercase, alphanumeric_lowercase, uppercase, lowercase, numeric, symbols, space), e.g. `lowercase+numeric+symbols`; literal characters with `chars:`, e.g. `chars:abc!@# é`; or a file with the characters with `file:<path>`"
     )]
     pub(crate) alphabet: String,
 
+    #[arg(
+        short,
+        long,
+        value_name = "wordlist",
+        conflicts_with_all = ["alphabet", "passphrase_length_range"],
+        help = "A wordlist file to read candidate passphrases from, one per line, instead of brute forcing the alphabet. Use `-` to read from stdin"
+    )]
+    pub(crate) wordlist: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "mask",
+        conflicts_with_all = ["alphabet", "wordlist", "passphrase_length_range"],
+        help = "A mask to generate candidate passphrases from, where each position draws from its own charset: `?l` (lowercase), `?u` (uppercase), `?d` (digits), `?s` (symbols), `?a` (all), `?h`/`?H` (hex), `?1`-`?4` (custom) or a literal character. E.g. `?u?l?l?l?d?d` or `Summer?d?d?s`"
+    )]
+    pub(crate) mask: Option<String>,
+
+    #[arg(
+        short = '1',
+        long,
+        value_name = "charset",
+        requires = "mask",
+        help = "Custom charset for `?1` on the mask, e.g. `?l?d` or `abc123`"
+    )]
+    pub(crate) custom_charset1: Option<String>,
+
+    #[arg(
+        short = '2',
+        long,
+        value_name = "charset",
+        requires = "mask",
+        help = "Custom charset for `?2` on the mask"
+    )]
+    pub(crate) custom_charset2: Option<String>,
+
+    #[arg(
+        short = '3',
+        long,
+        value_name = "charset",
+        requires = "mask",
+        help = "Custom charset for `?3` on the mask"
+    )]
+    pub(crate) custom_charset3: Option<String>,
+
+    #[arg(
+        short = '4',
+        long,
+        value_name = "charset",
+        requires = "mask",
+        help = "Custom charset for `?4` on the mask"
+    )]
+    pub(crate) custom_charset4: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "rules",
+        requires = "words",
+        help = "A hashcat-compatible rule file, one rule per line. Every candidate passphrase of the wordlist or mask is expanded into one variant per rule. As in hashcat, functions that would grow a word past 256 bytes leave it unchanged"
+    )]
+    pub(crate) rules: Option<PathBuf>,
+
     #[arg(
         short,
         long,
@@ -46,9 +119,42 @@ pub(crate) struct Arguments {
         short,
         long,
         value_name = "target_address",
-        help = "A known address from your wallet. It must be within `search_width` for it to be found"
+        required_unless_present_any = ["targets_file", "target_xpub", "target_fingerprint", "descriptor"],
+        help = "A known address from your wallet. It must be within `search_width` for it to be found. Can be repeated to match any of many addresses, of different types"
+    )]
+    pub(crate) target_address: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "targets_file",
+        help = "A file with known addresses from your wallet, one per line"
+    )]
+    pub(crate) targets_file: Option<PathBuf>,
+
+    #[arg(
+        short = 'x',
+        long,
+        value_name = "target_xpub",
+        conflicts_with_all = ["target_address", "targets_file"],
+        help = "A known account extended public key from your wallet, instead of addresses. SLIP-132 `ypub`/`zpub` (or `upub`/`vpub`) imply the address type and its standard derivation path, while plain `xpub`/`tpub` are searched at the standard derivation paths of every address type (BIP44, BIP49, BIP84 and BIP86)"
+    )]
+    pub(crate) target_xpub: Option<String>,
+
+    #[arg(
+        short = 'f',
+        long,
+        value_name = "target_fingerprint",
+        help = "The known BIP32 master key fingerprint of your wallet, e.g. `73c5da0a`. Only the master key is derived for each tried wallet, and fingerprint matches are confirmed against the target addresses or xpub, if any. Without them, the candidate space must be counted in advance and can't exceed 2^24 candidates, so fingerprints are unlikely to collide"
+    )]
+    pub(crate) target_fingerprint: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "descriptor",
+        conflicts_with_all = ["derivation_path", "coin_type", "keychains"],
+        help = "A descriptor template for your wallet, e.g. `wpkh([fp/84'/0'/0']/<0;1>/*)`, `sh(wpkh([fp/49'/0'/0']/<0;1>/*))` or `tr([73c5da0a/86'/0'/0']/0/*)`. Its keys are replaced by the ones derived from each tried wallet, and a known origin fingerprint is matched too. Can be repeated. Use this flag instead of `--derivation-path`, `--coin-type` and `--keychains`"
     )]
-    pub(crate) target_address: String,
+    pub(crate) descriptor: Vec<String>,
 
     #[arg(
         short,
@@ -58,6 +164,27 @@ pub(crate) struct Arguments {
     )]
     pub(crate) derivation_path: Option<String>,
 
+    #[arg(
+        short,
+        long,
+        value_name = "coin_type",
+        value_parser = parse_coin_type,
+        conflicts_with = "derivation_path",
+        help = "The BIP44 coin type used on the standard derivation path. Defaults to `0` on bitcoin and `1` on test networks. Use this flag if your wallet ignores the convention"
+    )]
+    pub(crate) coin_type: Option<u32>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "keychains",
+        value_delimiter = ',',
+        value_parser = parse_keychain,
+        default_value = "0,1",
+        help = "The keychains to derive addresses from. `0` is the external (receive) keychain and `1` is the internal (change) keychain"
+    )]
+    pub(crate) keychains: Vec<u32>,
+
     #[arg(
         short,
         long,
@@ -76,68 +203,202 @@ pub(crate) struct Arguments {
         help = "The passphrase lenght range to be searched. Will return an error if your address is not found within the search space"
     )]
     pub(crate) passphrase_length_range: (usize, usize),
+
+    #[arg(
+        long,
+        value_name = "part",
+        value_parser = parse_part,
+        help = "Only search part `k` out of `N` of the candidate space, e.g. `2/4`. Running all `N` parts, with the same arguments, searches the full candidate space without overlaps"
+    )]
+    pub(crate) part: Option<(usize, usize)>,
+
+    #[arg(
+        long,
+        value_name = "seed",
+        help = "The seed used to scramble the alphabet. Defaults to a random seed, or to `0` when searching a part of the candidate space"
+    )]
+    pub(crate) seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "session",
+        help = "A session file to periodically checkpoint progress to, and on Ctrl-C, so an interrupted run can be restored with `--restore`"
+    )]
+    pub(crate) session: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "session",
+        help = "Restore progress from the session file. All other arguments must be the same as the ones of the interrupted run"
+    )]
+    pub(crate) restore: bool,
+
+    #[arg(
+        long,
+        requires = "session",
+        conflicts_with = "restore",
+        help = "Overwrite the session file if it already exists. Otherwise, cracking refuses to start instead of losing the progress it holds"
+    )]
+    pub(crate) overwrite_session: bool,
+
+    #[arg(
+        long,
+        help = "Only count the candidate space and calibrate the cracking rate, to print the estimated time to exhaust it, without cracking"
+    )]
+    pub(crate) estimate: bool,
+
+    #[arg(
+        long,
+        value_name = "format",
+        default_value = "text",
+        value_parser = PossibleValuesParser::new(["text", "json"]),
+        help = "The output format. `json` prints a report as a single JSON object on stdout and progress events as JSON lines on stderr, in the versioned schema documented in the README"
+    )]
+    pub(crate) output: String,
+
+    #[arg(
+        long,
+        help = "Include the mnemonic and the master xpriv of the cracked wallet in the JSON report, which leaves them out by default"
+    )]
+    pub(crate) include_secrets: bool,
+
+    #[arg(
+        long,
+        value_name = "seconds",
+        default_value_t = 60,
+        help = "How often progress is checkpointed to the session file, in seconds"
+    )]
+    pub(crate) checkpoint_interval: u64,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Measure how many wallets per second go through each stage of cracking, for each address
+    /// type and thread count
+    Bench(BenchArguments),
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct BenchArguments {
+    #[arg(
+        short,
+        long,
+        value_name = "threads",
+        value_delimiter = ',',
+        value_parser = parse_threads,
+        help = "The thread counts to benchmark, e.g. `1,4,8`. Defaults to powers of two up to the available parallelism"
+    )]
+    pub(crate) threads: Option<Vec<usize>>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "seconds",
+        value_parser = parse_seconds,
+        default_value = "1",
+        help = "How long to measure each stage for, on each thread count, in seconds"
+    )]
+    pub(crate) duration: Duration,
+
+    #[arg(
+        long,
+        value_name = "format",
+        default_value = "text",
+        value_parser = PossibleValuesParser::new(["text", "json"]),
+        help = "The output format. `json` prints the results as a single JSON object instead of a table"
+    )]
+    pub(crate) output: String,
 }
 
-/// Parse the CLI arguments into a [`Wallet`].
-pub(crate) fn parse_cli_arguments(args: Arguments) -> Result<Wallet, HashsatError> {
+/// Parse the CLI arguments into a [`Cracker`].
+pub(crate) fn parse_cli_arguments(args: Arguments) -> Result<Cracker, HashsatError> {
     // Parse the mnemonic.
-    let mnemonic = Mnemonic::from_str(&args.mnemonic)?;
+    let mnemonic = Mnemonic::from_str(args.mnemonic.as_deref().unwrap_or_default())?;
+    let mut cracker = Cracker::builder(mnemonic);
     // Parse the passphrase alphabet.
-    let alphabet = args.alphabet;
+    cracker = cracker.alphabet(Alphabet::from_str(&args.alphabet)?);
+    // Get the wordlist, if provided.
+    if let Some(wordlist) = args.wordlist {
+        cracker = cracker.wordlist(wordlist);
+    }
+    // Parse the mask, if provided.
+    let custom_charsets = [
+        args.custom_charset1,
+        args.custom_charset2,
+        args.custom_charset3,
+        args.custom_charset4,
+    ];
+    if let Some(mask) = args.mask {
+        cracker = cracker.mask(Mask::new(&mask, &custom_charsets)?);
+    }
+    // Get the rule file, if provided.
+    if let Some(rules) = args.rules {
+        cracker = cracker.rules(rules);
+    }
     // Parse the network.
-    let network = Network::from_str(&args.network)?;
-    // Parse the target address.
-    let target_address = Address::from_str(&args.target_address)?.require_network(network)?;
-    // Parse the derivation path, if provided; or use the standard derivation path for the address
-    // type.
-    let derivation_path = if args.derivation_path.is_some() {
-        DerivationPath::from_str(&args.derivation_path.unwrap())?
-    } else {
-        match target_address.address_type() {
-            Some(AddressType::P2pkh) => DerivationPath::from_str("m/44'/0'/0'")?,
-            //Some(AddressType::P2sh) => DerivationPath::from_str("m/49'/0'/0'")?,
-            Some(AddressType::P2sh) => {
-                return Err(HashsatError::UnsupportedAddressType(
-                    AddressType::P2sh.to_string(),
-                ));
-            }
-            Some(AddressType::P2wpkh) => DerivationPath::from_str("m/84'/0'/0'")?,
-            //Some(AddressType::P2wsh) => DerivationPath::from_str("m/84'/0'/0'")?,
-            Some(AddressType::P2wsh) => {
-                return Err(HashsatError::UnsupportedAddressType(
-                    AddressType::P2tr.to_string(),
-                ));
-            }
-            //Some(AddressType::P2tr) => DerivationPath::from_str("m/86'/0'/0'")?,
-            Some(AddressType::P2tr) => {
-                return Err(HashsatError::UnsupportedAddressType(
-                    AddressType::P2tr.to_string(),
-                ));
-            }
-            _ => {
-                return Err(HashsatError::InvalidDerivationPath(
-                    bip32::Error::InvalidDerivationPathFormat,
-                ));
-            }
-        }
-    };
-    // Get the search width.
-    let search_width = args.search_width;
-    // Get the passphrase lenght range.
-    let passphrase_length_range = args.passphrase_length_range;
-
-    Ok(Wallet {
-        mnemonic,
-        alphabet,
-        target_address,
-        derivation_path,
-        search_width,
-        passphrase_length_range,
-        network,
-        passphrase: None,
-        xpub: None,
-        xpriv: None,
-    })
+    cracker = cracker.network(match args.network.as_str() {
+        "testnet3" => Network::Testnet,
+        network => Network::from_str(network)?,
+    });
+    // Parse the target addresses, from the arguments and from the targets file.
+    let mut targets = args.target_address;
+    if let Some(targets_file) = args.targets_file {
+        targets.extend(
+            fs::read_to_string(targets_file)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+    for target in targets {
+        cracker = cracker.target_address(Address::from_str(&target)?);
+    }
+    // Parse the target extended public key, if provided. Its version bytes imply its script type.
+    if let Some(xpub) = args.target_xpub {
+        let (xpub, script_type) = decode_xpub(&xpub)?;
+        cracker = cracker.target_xpub(xpub, script_type);
+    }
+    // Parse the target master key fingerprint, if provided.
+    if let Some(fingerprint) = args.target_fingerprint {
+        cracker = cracker.target_fingerprint(
+            Fingerprint::from_str(&fingerprint)
+                .map_err(|e| HashsatError::InvalidFingerprint(e.to_string()))?,
+        );
+    }
+    // Parse the descriptor templates, if provided; or use the standard descriptor for each of the
+    // target address types, on the given derivation path and keychains.
+    for descriptor in args.descriptor {
+        cracker = cracker.descriptor(Descriptor::from_str(&descriptor)?);
+    }
+    if let Some(derivation_path) = args.derivation_path {
+        cracker = cracker.derivation_path(DerivationPath::from_str(&derivation_path)?);
+    }
+    if let Some(coin_type) = args.coin_type {
+        cracker = cracker.coin_type(coin_type);
+    }
+    cracker = cracker.keychains(args.keychains);
+    // Get the search width and the passphrase lenght range.
+    let (min, max) = args.passphrase_length_range;
+    cracker = cracker
+        .search_width(args.search_width)
+        .passphrase_length_range(min, max);
+    // Get the partitioning parameters.
+    if let Some((k, n)) = args.part {
+        cracker = cracker.part(k, n);
+    }
+    if let Some(seed) = args.seed {
+        cracker = cracker.seed(seed);
+    }
+    // Get the session parameters.
+    if let Some(session) = args.session {
+        cracker = cracker.session(session);
+    }
+    cracker
+        .restore(args.restore)
+        .overwrite_session(args.overwrite_session)
+        .checkpoint_interval(Duration::from_secs(args.checkpoint_interval))
+        .build()
 }
 
 fn parse_range(s: &str) -> Result<(usize, usize), String> {
@@ -159,3 +420,306 @@ fn parse_range(s: &str) -> Result<(usize, usize), String> {
 
     Ok((min, max))
 }
+
+/// Parse a part in the `k/N` format, where `1 <= k <= N`.
+pub(crate) fn parse_part(s: &str) -> Result<(usize, usize), String> {
+    let (k, n) = s.split_once('/').ok_or("Part must be in format 'k/N'")?;
+
+    let k = k.parse::<usize>().map_err(|_| "Invalid part number")?;
+    let n = n.parse::<usize>().map_err(|_| "Invalid number of parts")?;
+
+    if k == 0 || k > n {
+        return Err("Part number must be between 1 and the number of parts".to_string());
+    }
+
+    Ok((k, n))
+}
+
+/// Parse a coin type, which must be below `2^31` as it is hardened on the derivation path.
+fn parse_coin_type(s: &str) -> Result<u32, String> {
+    match s.trim().parse::<u32>() {
+        Ok(coin_type) if coin_type < 1 << 31 => Ok(coin_type),
+        Ok(_) => Err(format!("Coin type `{s}` must be below 2^31")),
+        Err(_) => Err(format!("Invalid coin type `{s}`")),
+    }
+}
+
+/// Parse a keychain, which can't be hardened.
+fn parse_keychain(s: &str) -> Result<u32, String> {
+    match s.trim().parse::<u32>() {
+        Ok(keychain) if keychain < 1 << 31 => Ok(keychain),
+        Ok(_) => Err(format!("Keychain `{s}` can't be hardened")),
+        Err(_) => Err(format!("Invalid keychain `{s}`")),
+    }
+}
+
+/// Parse a thread count, which must be positive.
+pub(crate) fn parse_threads(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("Invalid thread count `{s}`")),
+        Ok(threads) => Ok(threads),
+    }
+}
+
+/// Parse a duration in seconds, e.g. `1` or `0.5`.
+pub(crate) fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| format!("Invalid duration `{s}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use hashsat::bitcoin::{CompressedPublicKey, bip32::Xpriv, key::Secp256k1};
+
+    use super::*;
+    use hashsat::testing::{TEST_MNEMONIC, TempFile, builder};
+
+    /// Parse the CLI arguments, after the mnemonic.
+    fn parse(args: &[&str]) -> Result<Cracker, HashsatError> {
+        parse_cli_arguments(
+            Arguments::try_parse_from([&["hashsat", "-m", TEST_MNEMONIC], args].concat()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn hardened_keychains() {
+        let address = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+        assert!(parse(&["-t", address, "-k", "0,2147483647"]).is_ok());
+        assert!(
+            Arguments::try_parse_from(["hashsat", "-m", "", "-t", address, "-k", "2147483648"])
+                .is_err()
+        );
+
+        let builder = builder().target_address(Address::from_str(address).unwrap());
+        assert!(matches!(
+            builder.keychains([0, 1 << 31]).build(),
+            Err(HashsatError::InvalidKeychain(2147483648))
+        ));
+    }
+
+    #[test]
+    fn passphrase_length_ranges() {
+        let address = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+        assert!(parse(&["-t", address, "-r", "0,0"]).is_ok());
+        assert!(
+            Arguments::try_parse_from(["hashsat", "-m", "", "-t", address, "-r", "5,2"]).is_err()
+        );
+
+        let builder = builder().target_address(Address::from_str(address).unwrap());
+        assert!(
+            builder
+                .clone()
+                .passphrase_length_range(2, 2)
+                .build()
+                .is_ok()
+        );
+        assert!(matches!(
+            builder.passphrase_length_range(5, 2).build(),
+            Err(HashsatError::InvalidLengthRange(5, 2))
+        ));
+    }
+
+    #[test]
+    fn hardened_coin_types() {
+        let address = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+        assert!(parse(&["-t", address, "-c", "2147483647"]).is_ok());
+        assert!(
+            Arguments::try_parse_from(["hashsat", "-m", "", "-t", address, "-c", "2147483648"])
+                .is_err()
+        );
+
+        let builder = builder().target_address(Address::from_str(address).unwrap());
+        assert!(matches!(
+            builder.coin_type(1 << 31).build(),
+            Err(HashsatError::InvalidCoinType(2147483648))
+        ));
+    }
+
+    #[test]
+    fn network_coin_types() {
+        // The first BIP84 address of the test mnemonic on test networks, at `m/84'/1'/0'/0/0`.
+        let testnet = Address::from_str("tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl")
+            .unwrap()
+            .assume_checked();
+        for (network, coin_type) in [
+            (Network::Bitcoin, 0),
+            (Network::Testnet, 1),
+            (Network::Testnet4, 1),
+            (Network::Signet, 1),
+            (Network::Regtest, 1),
+        ] {
+            let cracker = builder()
+                .network(network)
+                .target_address(
+                    Address::from_script(&testnet.script_pubkey(), network)
+                        .unwrap()
+                        .into_unchecked(),
+                )
+                .build()
+                .unwrap();
+            assert_eq!(
+                cracker.wallet().descriptors[0].account_path,
+                DerivationPath::from_str(&format!("m/84'/{coin_type}'/0'")).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn coin_type_override() {
+        // The first BIP84 address of the test mnemonic at `m/84'/5'/0'/0/0`.
+        let secp = Secp256k1::new();
+        let seed = Mnemonic::from_str(TEST_MNEMONIC).unwrap().to_seed("");
+        let path = DerivationPath::from_str("m/84'/5'/0'/0/0").unwrap();
+        let key = Xpriv::new_master(Network::Bitcoin, &seed)
+            .unwrap()
+            .derive_priv(&secp, &path)
+            .unwrap()
+            .to_priv();
+        let address = Address::p2wpkh(
+            &CompressedPublicKey::from_private_key(&secp, &key).unwrap(),
+            Network::Bitcoin,
+        )
+        .to_string();
+
+        let cracker = parse(&["-t", &address, "-c", "5", "-r", "0,0", "-s", "1"]).unwrap();
+        let descriptors: Vec<String> = cracker
+            .wallet()
+            .descriptors
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(descriptors, ["wpkh([fp/84'/5'/0']/<0;1>/*)"]);
+        let jackpot = cracker.crack().unwrap();
+        assert_eq!(jackpot.address.unwrap().to_string(), address);
+        assert_eq!(jackpot.derivation_path, path);
+    }
+
+    #[test]
+    fn rules_require_words() {
+        let rules = TempFile::new("rules-require-words", "$1\n");
+        let rules = rules.path().to_str().unwrap();
+        let address = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+
+        // Rules mangle the words of a wordlist or a mask, not brute forced passphrases.
+        assert!(
+            Arguments::try_parse_from(["hashsat", "-m", "", "-t", address, "--rules", rules])
+                .is_err()
+        );
+        assert!(parse(&["-t", address, "--mask", "?d", "--rules", rules]).is_ok());
+        assert!(parse(&["-t", address, "-w", "-", "--rules", rules]).is_ok());
+    }
+
+    /// First addresses of the test mnemonic, one of each type.
+    const P2PKH: &str = "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA";
+    const P2SH: &str = "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf";
+    const P2WPKH: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+    const P2TR: &str = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+
+    #[test]
+    fn mixed_targets() {
+        // Repeated flags of different types, deduplicated, each with its standard descriptor.
+        let cracker = parse(&[
+            "-t", P2WPKH, "-t", P2PKH, "-t", P2WPKH, "-t", P2SH, "-t", P2TR,
+        ])
+        .unwrap();
+        let wallet = cracker.wallet();
+        let addresses: Vec<String> = wallet
+            .target_addresses
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(addresses, [P2WPKH, P2PKH, P2SH, P2TR]);
+        let descriptors: Vec<String> = wallet.descriptors.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            descriptors,
+            [
+                "wpkh([fp/84'/0'/0']/<0;1>/*)",
+                "pkh([fp/44'/0'/0']/<0;1>/*)",
+                "sh(wpkh([fp/49'/0'/0']/<0;1>/*))",
+                "tr([fp/86'/0'/0']/<0;1>/*)",
+            ]
+        );
+
+        // Every one of them is matched, on its own path.
+        for (target, path) in [
+            (P2PKH, "m/44'/0'/0'/0/0"),
+            (P2SH, "m/49'/0'/0'/0/0"),
+            (P2WPKH, "m/84'/0'/0'/0/0"),
+            (P2TR, "m/86'/0'/0'/0/0"),
+        ] {
+            let jackpot = parse(&["-t", target, "-r", "0,0", "-s", "1"])
+                .unwrap()
+                .crack()
+                .unwrap();
+            assert_eq!(jackpot.address.unwrap().to_string(), target);
+            assert_eq!(
+                jackpot.derivation_path,
+                DerivationPath::from_str(path).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn targets_of_descriptors() {
+        // Target addresses must be of a type derived by one of the descriptors.
+        let descriptor = "wpkh([fp/84'/0'/0']/<0;1>/*)";
+        assert!(parse(&["-t", P2WPKH, "--descriptor", descriptor]).is_ok());
+        for target in [P2TR, P2PKH, P2SH] {
+            assert!(matches!(
+                parse(&["-t", P2WPKH, "-t", target, "--descriptor", descriptor]),
+                Err(HashsatError::UnsupportedAddressType(_))
+            ));
+        }
+        assert!(
+            parse(&[
+                "-t",
+                P2TR,
+                "--descriptor",
+                descriptor,
+                "--descriptor",
+                "tr([fp/86'/0'/0']/<0;1>/*)",
+            ])
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn parse_targets_file() {
+        // Comments, blank lines and surrounding whitespace are ignored, and duplicates of the
+        // flags are dropped.
+        let file = TempFile::new(
+            "targets-valid",
+            format!("# my wallet\n\n  {P2SH}  \n{P2WPKH}\r\n\n# change\n{P2TR}\n"),
+        );
+        let cracker = parse(&[
+            "-t",
+            P2WPKH,
+            "--targets-file",
+            file.path().to_str().unwrap(),
+        ]);
+        let addresses: Vec<String> = cracker
+            .unwrap()
+            .wallet()
+            .target_addresses
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(addresses, [P2WPKH, P2SH, P2TR]);
+
+        // Addresses of another network are rejected.
+        let file = TempFile::new(
+            "targets-testnet",
+            format!("{P2WPKH}\ntb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl\n"),
+        );
+        let cracker = parse(&["--targets-file", file.path().to_str().unwrap()]);
+        assert!(matches!(cracker, Err(HashsatError::InvalidAddress(_))));
+
+        // So are malformed addresses.
+        let file = TempFile::new("targets-malformed", "not an address\n");
+        let cracker = parse(&["--targets-file", file.path().to_str().unwrap()]);
+        assert!(matches!(cracker, Err(HashsatError::InvalidAddress(_))));
+    }
+}
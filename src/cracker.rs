@@ -1,387 +1,873 @@
 //! hashsat: a bitcoin passphrase cracker
 
 use std::{
-    io::{Write, stdout},
-    sync::{
-        Arc, Mutex,
-        atomic::{AtomicBool, AtomicUsize, Ordering},
-    },
-    thread,
-    time::{Duration, Instant},
+    fs,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Instant,
 };
 
-use bitcoin::{
-    Address, NetworkKind,
-    bip32::{ChildNumber, Xpriv, Xpub},
-    key::Secp256k1,
+use bip39::Mnemonic;
+use bitcoin::{Network, key::Secp256k1, secp256k1::All};
+use rand::{Rng, rng};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    builder::CrackerBuilder,
+    error::HashsatError,
+    estimate::{Estimate, preflight_keyspace},
+    events::{CancellationToken, Event, Stats, Subscriber},
+    matcher::{CandidateWallet, Matcher},
+    pbkdf2::SeedDeriver,
+    session::Session,
+    source::{CandidateSource, candidates},
+    types::{Jackpot, Wallet},
 };
-use rand::{rng, seq::SliceRandom};
-use rayon::iter::{ParallelBridge, ParallelIterator};
-
-use crate::{error::HashsatError, types::Wallet};
-
-#[rustfmt::skip]
-const ALPHABET_ALPHANUMERIC: &str ="0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
-const ALPHABET_ALPHANUMERIC_UPPERCASE: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const ALPHABET_ALPHANUMERIC_LOWERCASE: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
-const ALPHABET_UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const ALPHABET_LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
-const ALPHABET_NUMERIC: &str = "0123456789";
-
-const COMMAS: [&str; 4] = ["", ".", "..", "..."];
-const SPINNERS: [char; 4] = ['\\', '|', '/', '–'];
-
-/// Round-Robin iteration between different length passphrases.
-struct RoundRobinIter {
-    /// Iterators for each passphrase subset.
-    passphrase_subset_iters: Vec<Box<dyn Iterator<Item = String> + Send>>,
-    /// Current position in the Round-Robin cycle.
-    current_idx: usize,
-    /// Keep track of exhausted subsets.
-    exhausted_subsets: Vec<bool>,
+
+thread_local! {
+    /// The `secp256k1` context of each worker thread, so it is only allocated and randomized once
+    /// instead of for every candidate.
+    static SECP: Secp256k1<All> = Secp256k1::new();
 }
 
-impl RoundRobinIter {
-    fn new(min: usize, max: usize, alphabet: String) -> Self {
-        let mut passphrase_subset_iters = Vec::new();
-        for size in min..=max {
-            // Scramble the alphabet on every run so walks across
-            // the search space are random instead of lexicographical.
-            // This make sure that different runs walk different paths.
-            let mut chars: Vec<char> = alphabet.chars().collect();
-            chars.shuffle(&mut rng());
-            let scrambled_alphabet: String = chars.into_iter().collect();
-
-            let iter = generate_passphrases_of_size(size, scrambled_alphabet.clone());
-            passphrase_subset_iters.push(Box::new(iter) as Box<dyn Iterator<Item = String> + Send>);
-        }
-        let n_subsets = passphrase_subset_iters.len();
+/// How many candidates each thread tests in between checkpoints.
+const BATCH_SIZE_PER_THREAD: usize = 256;
 
-        Self {
-            passphrase_subset_iters,
-            current_idx: 0,
-            exhausted_subsets: vec![false; n_subsets],
-        }
-    }
+/// The most candidates to match against a 32-bit fingerprint alone, for a collision to have a
+/// chance of at most 1 in 256 of coming before the passphrase.
+const MAX_UNCONFIRMED_KEYSPACE: u128 = 1 << 24;
+
+/// A passphrase cracker for a [`Wallet`], configured with a [`CrackerBuilder`].
+///
+/// ```no_run
+/// use std::str::FromStr;
+///
+/// use hashsat::{Cracker, bip39::Mnemonic, bitcoin::Address};
+///
+/// let mnemonic = Mnemonic::from_str("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")?;
+/// let jackpot = Cracker::builder(mnemonic)
+///     .target_address(Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu")?)
+///     .passphrase_length_range(1, 4)
+///     .build()?
+///     .crack()?;
+/// println!("{}", jackpot.passphrase);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cracker {
+    /// The wallet to crack.
+    wallet: Wallet,
 }
 
-impl Iterator for RoundRobinIter {
-    type Item = String;
+impl Cracker {
+    /// Start configuring a cracker for the passphrase of `mnemonic`.
+    pub fn builder(mnemonic: Mnemonic) -> CrackerBuilder {
+        CrackerBuilder::new(mnemonic)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.passphrase_subset_iters.is_empty() {
-            return None;
-        }
+    /// A cracker for an already validated wallet.
+    pub(crate) fn new(wallet: Wallet) -> Self {
+        Self { wallet }
+    }
 
-        let start_idx = self.current_idx;
-        loop {
-            if !self.exhausted_subsets[self.current_idx] {
-                if let Some(passphrase) = self.passphrase_subset_iters[self.current_idx].next() {
-                    self.current_idx = (self.current_idx + 1) % self.passphrase_subset_iters.len();
-                    return Some(passphrase);
-                } else {
-                    self.exhausted_subsets[self.current_idx] = true;
-                }
-            }
-            self.current_idx = (self.current_idx + 1) % self.passphrase_subset_iters.len();
+    /// The wallet being cracked.
+    pub fn wallet(&self) -> &Wallet {
+        &self.wallet
+    }
 
-            if self.current_idx == start_idx
-                && self.exhausted_subsets.iter().all(|&exhausted| exhausted)
-            {
-                return None;
-            }
-        }
+    /// Count the candidates, if they can be counted up front without reading a wordlist through.
+    /// Brute force and mask candidates are counted arithmetically, so this is cheap.
+    pub fn keyspace(&self) -> Result<Option<u128>, HashsatError> {
+        preflight_keyspace(&self.wallet)
     }
-}
 
-/// Crack the passphrase with [`ALPHABET`] until [`MAX_PASSPHRASE_LEN`] is depleted.
-/// TODO(@luisschwab): make this async and parallel.
-pub(crate) fn crack(wallet: &mut Wallet) -> Result<(), HashsatError> {
-    // Hide the cursor.
-    print!("\x1b[?25l");
-    stdout().flush()?;
+    /// Count the candidates and calibrate the cracking rate, to estimate how long it takes to
+    /// exhaust the candidate space.
+    pub fn estimate(&self) -> Result<Estimate, HashsatError> {
+        Estimate::new(&self.wallet)
+    }
 
-    println!("\nspinning up hashers...\n");
-    std::thread::sleep(Duration::from_secs(1));
-    print_cracking_params(wallet);
+    /// Crack the passphrase, until it is found or the candidate space is depleted.
+    pub fn crack(&self) -> Result<Jackpot, HashsatError> {
+        self.crack_with(&CancellationToken::new(), |_: &Event| {})
+    }
 
-    // Select the alphabet.
-    let alphabet = get_alphabet(&wallet.alphabet);
+    /// Crack the passphrase, until it is found, the candidate space is depleted or `cancel` is
+    /// cancelled, reporting progress to `subscriber` along the way.
+    ///
+    /// ```no_run
+    /// # use std::{str::FromStr, sync::mpsc, thread};
+    /// # use hashsat::{CancellationToken, Cracker, Event, bip39::Mnemonic, bitcoin::Address};
+    /// # let mnemonic = Mnemonic::from_str("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")?;
+    /// let cracker = Cracker::builder(mnemonic)
+    ///     .target_address(Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu")?)
+    ///     .build()?;
+    /// let cancel = CancellationToken::new();
+    /// let (sender, receiver) = mpsc::channel();
+    ///
+    /// let worker = {
+    ///     let cancel = cancel.clone();
+    ///     thread::spawn(move || cracker.crack_with(&cancel, sender))
+    /// };
+    /// for event in receiver {
+    ///     if let Event::Progress { stats, .. } = event {
+    ///         println!("{} tries at {:.0} wallets/s", stats.tries, stats.rate);
+    ///         if stats.tries > 1_000_000 {
+    ///             cancel.cancel();
+    ///         }
+    ///     }
+    /// }
+    /// let result = worker.join().unwrap();
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn crack_with(
+        &self,
+        cancel: &CancellationToken,
+        mut subscriber: impl Subscriber,
+    ) -> Result<Jackpot, HashsatError> {
+        crack(&self.wallet, cancel, &mut subscriber)
+    }
+}
 
-    // Passphrase ranges.
-    let min = wallet.passphrase_length_range.0;
-    let max = wallet.passphrase_length_range.1;
+/// Crack the passphrase of a wallet, testing candidates until the candidate space is depleted or
+/// the run is cancelled.
+fn crack(
+    wallet: &Wallet,
+    cancel: &CancellationToken,
+    subscriber: &mut dyn Subscriber,
+) -> Result<Jackpot, HashsatError> {
+    // Refuse to overwrite a session that isn't restored, unless asked to, as it can hold a long
+    // run's progress.
+    if let Some(path) = &wallet.session
+        && !wallet.restore
+        && !wallet.overwrite_session
+        && path.exists()
+    {
+        return Err(HashsatError::SessionExists(path.display().to_string()));
+    }
+    // Load the session to restore, if any.
+    let session = Session::restore(wallet)?;
+    // The hash of the configuration to checkpoint the session with, if any, which the restored
+    // session was already checked against.
+    let config = match (&session, &wallet.session) {
+        (Some(session), _) => Some(session.config),
+        (None, Some(_)) => Some(Session::config_hash(wallet)?),
+        (None, None) => None,
+    };
+    // The seed used to scramble the alphabet.
+    let seed = match (&session, wallet.seed) {
+        (Some(session), _) => session.seed,
+        (None, Some(seed)) => seed,
+        (None, None) => rng().random(),
+    };
+
+    // Candidate passphrases, from whichever source the wallet is configured with.
+    let mut candidates = candidates(wallet, seed)?;
+    // What candidate wallets are matched against.
+    let matcher = wallet.matcher()?;
+    // The cracker stops at the first match, so a fingerprint alone must be unlikely to collide
+    // before the passphrase is found, which can't be told without counting the candidates.
+    if wallet.is_fingerprint_only() {
+        match candidates.keyspace() {
+            Some(keyspace) if keyspace > MAX_UNCONFIRMED_KEYSPACE => {
+                return Err(HashsatError::UnconfirmedFingerprint(keyspace));
+            }
+            None => return Err(HashsatError::UncountedFingerprint),
+            Some(_) => {}
+        }
+    }
+
+    // Pick up where the restored session stopped, which sources that can't seek can't do.
+    let restored_tries = match (&session, &wallet.session) {
+        (Some(session), Some(path)) => {
+            if !candidates.seek(&session.position) {
+                return Err(HashsatError::UnseekableSession(path.display().to_string()));
+            }
+            session.tries
+        }
+        _ => 0,
+    };
 
-    // Thread-common state.
-    let found = Arc::new(AtomicBool::new(false));
-    let tries_ctr = Arc::new(AtomicUsize::new(0));
-    let curr_passphrase = Arc::new(Mutex::new(String::new()));
+    // Derive seeds with the fastest SIMD backend this CPU supports.
+    let seed_deriver = SeedDeriver::new(&wallet.mnemonic);
 
-    // Start time.
+    let tries_ctr = AtomicUsize::new(restored_tries);
     let start = Instant::now();
+    let stats = |tries: usize| Stats::new(tries, restored_tries, start.elapsed());
 
-    // Progress thread.
-    let found_clone = found.clone();
-    let tries_ctr_clone = tries_ctr.clone();
-    let curr_passphrase_clone = curr_passphrase.clone();
-    let progress_handle = thread::spawn(move || {
-        let (mut comma_idx, mut spinner_idx) = (0, 0);
-
-        while !found_clone.load(Ordering::Relaxed) {
-            thread::sleep(Duration::from_millis(100));
-
-            let tries = tries_ctr_clone.load(Ordering::Relaxed);
-            if tries > 0 {
-                let elapsed = start.elapsed();
-                let curr_passphrase = curr_passphrase_clone.lock().unwrap().clone();
-
-                print!(
-                    "\r{} cracking sats : {} ({} wallets in {}){:<3}",
-                    SPINNERS[spinner_idx],
-                    curr_passphrase,
-                    format_number(tries),
-                    format_duration(elapsed),
-                    COMMAS[comma_idx],
-                );
-                stdout().flush().unwrap_or(());
-
-                comma_idx = (comma_idx + 1) % COMMAS.len();
-                spinner_idx = (spinner_idx + 1) % SPINNERS.len();
-            }
-        }
+    subscriber.on_event(&Event::Started {
+        seed,
+        tries: restored_tries,
     });
 
-    // Round-Robin iterator: join subsets into a unified iterator.
-    let rr_iter = RoundRobinIter::new(min, max, alphabet.to_string());
-    let crack_res = rr_iter.par_bridge().find_any(|passphrase| {
-        // Update progress counter
-        let tries = tries_ctr.fetch_add(1, Ordering::Relaxed);
+    // Test candidates in batches, so the session can be checkpointed, progress reported and
+    // cancellation checked in between them without skipping any candidate.
+    let batch_size = BATCH_SIZE_PER_THREAD * rayon::current_num_threads();
+    let lanes = seed_deriver.lanes();
+    let mut last_checkpoint = Instant::now();
+    let mut skipped = 0;
+    let crack_res = loop {
+        let tries = tries_ctr.load(Ordering::Relaxed);
+
+        // Seeds are derived in chunks, one candidate per SIMD lane. Each chunk keeps the position
+        // it starts at, to checkpoint a batch interrupted by cancellation from.
+        let mut batch: Vec<(Vec<u128>, Vec<String>)> = Vec::new();
+        let mut pulled = 0;
+        while pulled < batch_size {
+            let position = candidates.position();
+            let chunk: Vec<String> = candidates
+                .by_ref()
+                .take(lanes.min(batch_size - pulled))
+                .collect();
+            if chunk.is_empty() {
+                break;
+            }
+            pulled += chunk.len();
+            batch.push((position, chunk));
+        }
+        // Report candidates the source skipped, e.g. wordlist lines that are not valid UTF-8.
+        if candidates.skipped() > skipped {
+            skipped = candidates.skipped();
+            subscriber.on_event(&Event::Skipped { skipped });
+        }
+        // Which chunks were tested in full, as workers may finish them out of order.
+        let tested: Vec<AtomicBool> = batch.iter().map(|_| AtomicBool::new(false)).collect();
+
+        let batch_res = batch
+            .par_iter()
+            .enumerate()
+            .find_map_any(|(i, (_, chunk))| {
+                // Drain the rest of the batch quickly once cancelled.
+                if cancel.is_cancelled() {
+                    return None;
+                }
 
-        // Update the progress bar with the current passphrase every once in a while.
-        if tries % 69 == 0 {
-            *curr_passphrase.lock().unwrap() = passphrase.clone();
+                // Test and assert each passphrase against the wallet parameters.
+                // `find_map_any` will return the findings if they are `Some()`.
+                let seeds = seed_deriver.seeds(chunk);
+                let found = SECP.with(|secp| {
+                    chunk
+                        .iter()
+                        .zip(&seeds)
+                        .enumerate()
+                        .find_map(|(j, (passphrase, seed))| {
+                            derive_wallet_and_assert_with(
+                                secp,
+                                &matcher,
+                                wallet.network,
+                                passphrase,
+                                seed,
+                            )
+                            .map(|jackpot| (j, jackpot))
+                        })
+                });
+                // Count only the candidates tested, up to and including a match.
+                let count = found.as_ref().map_or(chunk.len(), |(j, _)| j + 1);
+                tries_ctr.fetch_add(count, Ordering::Relaxed);
+                tested[i].store(found.is_none(), Ordering::Relaxed);
+                found.map(|(_, jackpot)| jackpot)
+            });
+        if batch_res.is_some() {
+            break batch_res;
         }
 
-        // Test and assert this passphrase against the wallet
-        // parameters. `find_any` will return the findings if they are `Some()`.
-        derive_wallet_and_assert(wallet, passphrase).is_some()
-    });
+        // Stop, checkpointing the session right after the chunks tested in full from the start
+        // of the interrupted batch, so only chunks tested out of order are tested again on
+        // restore. This comes before checking for exhaustion, as the last batch may have been
+        // cut short too.
+        if cancel.is_cancelled() {
+            if let (Some(path), Some(config)) = (&wallet.session, config) {
+                let done = tested
+                    .iter()
+                    .take_while(|tested| tested.load(Ordering::Relaxed))
+                    .count();
+                let position = match batch.get(done) {
+                    Some((position, _)) => position.clone(),
+                    None => candidates.position(),
+                };
+                let tries = tries + batch[..done].iter().map(|(_, c)| c.len()).sum::<usize>();
+                let session = Session {
+                    config,
+                    seed,
+                    position,
+                    tries,
+                };
+                checkpoint(&session, path, subscriber);
+            }
+            subscriber.on_event(&Event::Cancelled {
+                stats: stats(tries_ctr.load(Ordering::Relaxed)),
+            });
+            return Err(HashsatError::Cancelled);
+        }
+        if pulled < batch_size {
+            // The candidates ended early on an error, so checkpoint the session to pick up from
+            // there once it is fixed, instead of reporting exhaustion.
+            if let Some(e) = candidates.take_error() {
+                if let (Some(path), Some(config)) = (&wallet.session, config) {
+                    let session = Session {
+                        config,
+                        seed,
+                        position: candidates.position(),
+                        tries: tries_ctr.load(Ordering::Relaxed),
+                    };
+                    checkpoint(&session, path, subscriber);
+                }
+                subscriber.on_event(&Event::Failed {
+                    error: e.to_string(),
+                    stats: stats(tries_ctr.load(Ordering::Relaxed)),
+                });
+                return Err(e);
+            }
+            break None;
+        }
 
-    // Signal progress thread to stop.
-    found.store(true, Ordering::Relaxed);
-    progress_handle.join().unwrap();
+        subscriber.on_event(&Event::Progress {
+            stats: stats(tries_ctr.load(Ordering::Relaxed)),
+            candidate: batch[0].1[0].clone(),
+        });
+
+        // Checkpoint the session every once in a while.
+        if let (Some(path), Some(config)) = (&wallet.session, config)
+            && last_checkpoint.elapsed() >= wallet.checkpoint_interval
+        {
+            let session = Session {
+                config,
+                seed,
+                position: candidates.position(),
+                tries: tries_ctr.load(Ordering::Relaxed),
+            };
+            checkpoint(&session, path, subscriber);
+            last_checkpoint = Instant::now();
+        }
+    };
 
-    let total_tries = tries_ctr.load(Ordering::Relaxed);
-    let elapsed = start.elapsed();
+    // The passphrase was found or the candidate space exhausted, so there is nothing left to
+    // restore.
+    if let Some(path) = &wallet.session {
+        let _ = fs::remove_file(path);
+    }
 
+    let stats = stats(tries_ctr.load(Ordering::Relaxed));
     match crack_res {
         Some(jackpot) => {
-            if let Some((passphrase, xpub, xpriv)) = derive_wallet_and_assert(wallet, &jackpot) {
-                wallet.passphrase = Some(passphrase.clone());
-                wallet.xpub = Some(xpub);
-                wallet.xpriv = Some(xpriv);
-
-                print!(
-                    "\r{} cracking sats : {} ({} wallets in {}){:<3}",
-                    SPINNERS[0],
-                    passphrase,
-                    format_number(total_tries),
-                    format_duration(elapsed),
-                    COMMAS[0],
-                );
-                stdout().flush()?;
-
-                println!("\n\nJACKPOT!");
-                println!(
-                    "hashsat found your lost sats in {} and {} tries ({} wallets per second)\n",
-                    format_duration(elapsed),
-                    format_number(total_tries),
-                    if elapsed.as_secs() > 0 {
-                        total_tries as u64 / elapsed.as_secs()
-                    } else {
-                        total_tries as u64
-                    }
-                );
-                println!("{wallet}");
-
-                // Unhide the cursor.
-                print!("\x1b[?25h");
-
-                Ok(())
-            } else {
-                Ok(())
-            }
+            subscriber.on_event(&Event::Found {
+                jackpot: Box::new(jackpot.clone()),
+                stats,
+            });
+
+            Ok(jackpot)
         }
         None => {
-            // Unhide the cursor.
-            print!("\x1b[?25h");
-
-            println!("\nSearch space depleted without finding passphrase");
-            Err(HashsatError::DepletedSearchSpace(min, max))
+            subscriber.on_event(&Event::Exhausted { stats });
+            match (&wallet.generator, &wallet.wordlist, &wallet.mask) {
+                (Some(_), _, _) => Err(HashsatError::DepletedCandidates),
+                (_, Some(path), _) => {
+                    Err(HashsatError::DepletedWordlist(path.display().to_string()))
+                }
+                (_, _, Some(mask)) => Err(HashsatError::DepletedMask(mask.to_string())),
+                _ => {
+                    let (min, max) = wallet.passphrase_length_range;
+                    Err(HashsatError::DepletedSearchSpace(min, max))
+                }
+            }
         }
     }
 }
 
-#[allow(dead_code)]
-/// Generate all candidate passphrases up to size `size` using the `Radix Conversion` algorithm.
-///
-/// Rust iterators are lazy (they're only evaluated when used),
-/// so we are not allocating a shit ton of memory with all passphrase combinations.
-fn generate_passphrases_up_to(size: usize, alphabet: String) -> impl Iterator<Item = String> {
-    (1..=size).flat_map(move |length| {
-        let chars: Vec<char> = alphabet.chars().collect();
-        (0..(chars.len() as u128).pow(length as u32)).map(move |mut n| {
-            let mut result = String::new();
-            for _ in 0..length {
-                result.push(chars[n as usize % chars.len()]);
-                n /= chars.len() as u128;
-            }
-            result
-        })
-    })
+/// Save a session checkpoint, and tell the subscriber how it went.
+fn checkpoint(session: &Session, path: &Path, subscriber: &mut dyn Subscriber) {
+    subscriber.on_event(&match session.save(path) {
+        Ok(()) => Event::Checkpointed {
+            path: path.to_path_buf(),
+            tries: session.tries,
+        },
+        Err(e) => Event::CheckpointFailed {
+            path: path.to_path_buf(),
+            error: e.to_string(),
+        },
+    });
 }
 
-/// Generate all candidate passphrases of size `size` using the `Radix Conversion` algorithm.
+/// Create a BIP32 wallet from an already derived seed, with a given `secp256k1` context, and
+/// test it with `matcher`.
 ///
-/// Rust iterators are lazy (they're only evaluated when used),
-/// so we are not allocating a shit ton of memory with all passphrase combinations.
-fn generate_passphrases_of_size(size: usize, alphabet: String) -> impl Iterator<Item = String> {
-    let chars: Vec<char> = alphabet.chars().collect();
-    (0..(chars.len() as u128).pow(size as u32)).map(move |mut n| {
-        let mut result = String::new();
-        for _ in 0..size {
-            result.push(chars[n as usize % chars.len()]);
-            n /= chars.len() as u128;
-        }
-        result
+/// Returns a [`Jackpot`] if the wallet matches. If not, returns None.
+pub(crate) fn derive_wallet_and_assert_with(
+    secp: &Secp256k1<All>,
+    matcher: &dyn Matcher,
+    network: Network,
+    passphrase: &str,
+    seed: &[u8; 64],
+) -> Option<Jackpot> {
+    let wallet = CandidateWallet::new(secp, passphrase, seed, network);
+    let found = matcher.matches(&wallet)?;
+
+    Some(Jackpot {
+        passphrase: passphrase.to_owned(),
+        xpub: *wallet.master_xpub(),
+        xpriv: *wallet.master_xpriv(),
+        address: found.address,
+        derivation_path: found.derivation_path,
     })
 }
 
-/// Create a BIP32 wallet from seed and passphrase,
-/// derive `derivation_width` addresses and see if any match `target_address`.
-///
-/// Returns ([`Passphrase`, `Xpub`, `Xpriv`]) if `target_address` is within the wallet. If not,
-/// returns None.
-fn derive_wallet_and_assert(wallet: &Wallet, passphrase: &String) -> Option<(String, Xpub, Xpriv)> {
-    // Spawn `secp256k1` context.
-    let secp = Secp256k1::new();
-
-    // Derive the seed from mnemonic and passphrase.
-    let seed = wallet.mnemonic.to_seed_normalized(passphrase);
-
-    // Create the master extended private and public keys.
-    let master_xpriv = Xpriv::new_master(NetworkKind::from(wallet.network), &seed).unwrap();
-    let master_xpub = Xpub::from_priv(&secp, &master_xpriv);
-
-    // Derive some addresses from external and internal keychains and see if they are a match.
-    let mut addresses: Vec<Address> = Vec::new();
-    for keychain_kind in 0..1 {
-        for idx in 0..wallet.search_width {
-            // Append `/keychain_kind` (keychain kind).
-            let derivation_path = wallet.derivation_path.child(ChildNumber::Normal {
-                index: keychain_kind,
-            });
-            // Append `/idx` (child number).
-            let derivation_path = derivation_path.child(ChildNumber::Normal { index: idx as u32 });
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{
+        Address, Network,
+        bip32::{DerivationPath, Fingerprint, Xpriv},
+    };
+
+    use super::*;
+    use crate::{
+        alphabet::Alphabet,
+        descriptor::ScriptType,
+        mask::Mask,
+        matcher::Match,
+        slip132::decode_xpub,
+        testing::{TEST_MNEMONIC, TempFile, builder},
+    };
+
+    /// Derive the seed and test a passphrase against a wallet.
+    fn derive_wallet_and_assert(wallet: &Wallet, passphrase: &str) -> Option<Jackpot> {
+        let seed = wallet.mnemonic.to_seed_normalized(passphrase);
+
+        let matcher = wallet.matcher().unwrap();
+
+        SECP.with(|secp| {
+            derive_wallet_and_assert_with(secp, &matcher, wallet.network, passphrase, &seed)
+        })
+    }
 
-            let xpriv = master_xpriv.derive_priv(&secp, &derivation_path).unwrap();
-            let xpub = Xpub::from_priv(&secp, &xpriv);
-            let compressed_pubkey = xpub.to_pub();
+    fn wallet(
+        target_address: &str,
+        derivation_path: &str,
+        search_width: usize,
+        network: Network,
+    ) -> Wallet {
+        builder()
+            .alphabet(Alphabet::from_str("numeric").unwrap())
+            .network(network)
+            .target_address(Address::from_str(target_address).unwrap())
+            .derivation_path(DerivationPath::from_str(derivation_path).unwrap())
+            .search_width(search_width)
+            .passphrase_length_range(0, 0)
+            .build()
+            .unwrap()
+            .wallet()
+            .clone()
+    }
 
-            let address = match wallet.derivation_path[0] {
-                ChildNumber::Hardened { index: 44 } => {
-                    Address::p2pkh(compressed_pubkey, wallet.network)
-                }
-                // TODO(@luisschwab): can we even support P2SH? I don't think so.
-                //ChildNumber::Hardened { index: 49 } => Address::p2sh(&compressed_pubkey,
-                // wallet.network).unwrap(),
-                ChildNumber::Hardened { index: 84 } => {
-                    Address::p2wpkh(&compressed_pubkey, wallet.network)
-                }
-                // TODO(@luisschwab): figure out Taproot addresses. Just use the internal key?
-                //ChildNumber::Hardened { index: 86 } => Address::p2tr(&compressed_pubkey,
-                // wallet.network),
-                _ => Address::p2wpkh(&compressed_pubkey, wallet.network),
-            };
-            addresses.push(address);
+    /// Test vectors from BIP86.
+    #[test]
+    fn bip86_key_path_tweak() {
+        let first = wallet(
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            "m/86'/0'/0'",
+            1,
+            Network::Bitcoin,
+        );
+        assert!(derive_wallet_and_assert(&first, "").is_some());
+
+        let second = wallet(
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh",
+            "m/86'/0'/0'",
+            2,
+            Network::Bitcoin,
+        );
+        assert!(derive_wallet_and_assert(&second, "").is_some());
+
+        // Outside of the search width.
+        let second = wallet(
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh",
+            "m/86'/0'/0'",
+            1,
+            Network::Bitcoin,
+        );
+        assert!(derive_wallet_and_assert(&second, "").is_none());
+
+        // Internal keychain.
+        let change = wallet(
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7",
+            "m/86'/0'/0'",
+            1,
+            Network::Bitcoin,
+        );
+        let jackpot = derive_wallet_and_assert(&change, "").unwrap();
+        assert_eq!(jackpot.address.as_ref(), change.target_addresses.first());
+        assert_eq!(
+            jackpot.derivation_path,
+            DerivationPath::from_str("m/86'/0'/0'/1/0").unwrap()
+        );
+    }
+
+    /// Test vector from BIP49.
+    #[test]
+    fn bip49_nested_segwit() {
+        let wallet = wallet(
+            "2Mww8dCYPUpKHofjgcXcBCEGmniw9CoaiD2",
+            "m/49'/1'/0'",
+            1,
+            Network::Testnet,
+        );
+        assert!(derive_wallet_and_assert(&wallet, "").is_some());
+    }
+
+    /// Account extended public keys from BIP84, with SLIP-132 version bytes.
+    #[test]
+    fn slip132_account_xpub() {
+        let (xpub, script_type) = decode_xpub("zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs").unwrap();
+        assert_eq!(script_type, Some(ScriptType::Wpkh));
+
+        let mut wallet = wallet(
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu",
+            "m/84'/0'/0'",
+            1,
+            Network::Bitcoin,
+        );
+        wallet.target_xpub = Some(xpub);
+        let jackpot = derive_wallet_and_assert(&wallet, "").unwrap();
+        assert_eq!(jackpot.address, None);
+        assert_eq!(
+            jackpot.derivation_path,
+            DerivationPath::from_str("m/84'/0'/0'").unwrap()
+        );
+
+        assert!(derive_wallet_and_assert(&wallet, "TREZOR").is_none());
+    }
+
+    /// The master key fingerprint of the BIP84 test mnemonic.
+    #[test]
+    fn master_fingerprint() {
+        let fingerprint = Fingerprint::from_str("73c5da0a").unwrap();
+
+        // Fingerprint only.
+        let mut wallet = wallet(
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu",
+            "m/84'/0'/0'",
+            1,
+            Network::Bitcoin,
+        );
+        wallet.target_fingerprint = Some(fingerprint);
+        let mut fingerprint_only = wallet.clone();
+        fingerprint_only.target_addresses.clear();
+        fingerprint_only.descriptors.clear();
+        assert!(derive_wallet_and_assert(&fingerprint_only, "").is_some());
+        assert!(derive_wallet_and_assert(&fingerprint_only, "TREZOR").is_none());
+
+        // Too many candidates for a fingerprint alone.
+        let cracker = Cracker::new(Wallet {
+            mask: Some(Mask::new("?a?a?a?a?a", &[None, None, None, None]).unwrap()),
+            ..fingerprint_only.clone()
+        });
+        assert!(matches!(
+            cracker.crack(),
+            Err(HashsatError::UnconfirmedFingerprint(7_737_809_375))
+        ));
+        let cracker = Cracker::new(Wallet {
+            mask: Some(Mask::new("?a?a?a?a", &[None, None, None, None]).unwrap()),
+            ..fingerprint_only.clone()
+        });
+        assert!(matches!(
+            cracker.crack(),
+            Err(HashsatError::UnconfirmedFingerprint(81_450_625))
+        ));
+
+        // Or candidates that can't be counted.
+        let candidates = || Candidates(vec![String::new()].into_iter());
+        let cracker = builder()
+            .candidates(candidates)
+            .target_fingerprint(fingerprint)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            cracker.crack(),
+            Err(HashsatError::UncountedFingerprint)
+        ));
+
+        // Fingerprint matches are confirmed against the target address.
+        assert!(derive_wallet_and_assert(&wallet, "").is_some());
+        wallet.search_width = 0;
+        assert!(derive_wallet_and_assert(&wallet, "").is_none());
+    }
+
+    /// Candidates from a fixed list, without size hints or seeking.
+    struct Candidates(std::vec::IntoIter<String>);
+
+    impl Iterator for Candidates {
+        type Item = String;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next()
         }
     }
 
-    if addresses.contains(&wallet.target_address) {
-        Some((passphrase.to_owned(), master_xpub, master_xpriv))
-    } else {
-        None
+    impl CandidateSource for Candidates {}
+
+    #[test]
+    fn custom_candidate_source() {
+        let candidates = || Candidates(vec!["foo".to_string(), String::new()].into_iter());
+        let cracker = builder()
+            .candidates(candidates)
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .search_width(1)
+            .build()
+            .unwrap();
+        assert_eq!(cracker.estimate().unwrap().candidates, None);
+        assert_eq!(cracker.crack().unwrap().passphrase, "");
+
+        // It can't seek, so it can't be restored instead of restarting from its first candidate.
+        let session = TempFile::new("unseekable-session", "");
+        let cracker = builder()
+            .candidates(candidates)
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .session(session.path())
+            .restore(true)
+            .build()
+            .unwrap();
+        Session {
+            config: Session::config_hash(cracker.wallet()).unwrap(),
+            seed: 0,
+            position: Vec::new(),
+            tries: 1,
+        }
+        .save(session.path())
+        .unwrap();
+        assert!(matches!(
+            cracker.crack(),
+            Err(HashsatError::UnseekableSession(_))
+        ));
     }
-}
 
-/// Format a [`Duration`] in the `hh:mm:ss` format.
-fn format_duration(duration: std::time::Duration) -> String {
-    let total_seconds = duration.as_secs();
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let seconds = total_seconds % 60;
-
-    if hours > 0 {
-        format!("{hours}h {minutes}m {seconds}s")
-    } else if minutes > 0 {
-        format!("{minutes}m {seconds}s")
-    } else {
-        format!("{seconds}s")
+    #[test]
+    fn existing_session() {
+        let session = TempFile::new("existing-session", "weeks of progress");
+        let builder = builder()
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .passphrase_length_range(0, 0)
+            .search_width(1)
+            .session(session.path());
+
+        // A session that isn't restored is only overwritten when asked to.
+        assert!(matches!(
+            builder.clone().build().unwrap().crack(),
+            Err(HashsatError::SessionExists(_))
+        ));
+        assert_eq!(
+            fs::read_to_string(session.path()).unwrap(),
+            "weeks of progress"
+        );
+        assert!(
+            builder
+                .overwrite_session(true)
+                .build()
+                .unwrap()
+                .crack()
+                .is_ok()
+        );
     }
-}
 
-/// Format a number with commas as the thousands separator.
-fn format_number(n: usize) -> String {
-    n.to_string()
-        .chars()
-        .rev()
-        .collect::<Vec<_>>()
-        .chunks(3)
-        .map(|chunk| chunk.iter().collect::<String>())
-        .collect::<Vec<_>>()
-        .join(",")
-        .chars()
-        .rev()
-        .collect()
-}
+    #[test]
+    fn custom_matcher() {
+        let candidates = || Candidates(vec!["foo".to_string(), String::new()].into_iter());
+        let foo = |wallet: &CandidateWallet| (wallet.passphrase() == "foo").then(Match::default);
+        let cracker = builder().candidates(candidates).matcher(foo);
+        assert_eq!(
+            cracker.clone().build().unwrap().crack().unwrap().passphrase,
+            "foo"
+        );
+
+        // Custom matchers must match on top of the targets.
+        let fingerprint = cracker
+            .clone()
+            .target_fingerprint(Fingerprint::from_str("73c5da0a").unwrap())
+            .build()
+            .unwrap();
+        assert!(matches!(
+            fingerprint.crack(),
+            Err(HashsatError::DepletedCandidates)
+        ));
+
+        // The matched address is kept, even though the custom matcher doesn't match on one.
+        let empty = |wallet: &CandidateWallet| wallet.passphrase().is_empty().then(Match::default);
+        let jackpot = cracker
+            .matcher(empty)
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .search_width(1)
+            .build()
+            .unwrap()
+            .crack()
+            .unwrap();
+        assert_eq!(
+            jackpot.address.unwrap().to_string(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        assert_eq!(
+            jackpot.derivation_path,
+            DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap()
+        );
+    }
 
-/// Get the alphabet from it's identifier.
-fn get_alphabet(identifier: &str) -> &'static str {
-    match identifier {
-        "alphanumeric" => ALPHABET_ALPHANUMERIC,
-        "alphanumeric_uppercase" => ALPHABET_ALPHANUMERIC_UPPERCASE,
-        "alphanumeric_lowercase" => ALPHABET_ALPHANUMERIC_LOWERCASE,
-        "uppercase" => ALPHABET_UPPERCASE,
-        "lowercase" => ALPHABET_LOWERCASE,
-        "numeric" => ALPHABET_NUMERIC,
-        _ => ALPHABET_ALPHANUMERIC,
+    #[test]
+    fn events_and_cancellation() {
+        let candidates = || Candidates(vec!["foo".to_string(), String::new()].into_iter());
+        let cracker = builder()
+            .candidates(candidates)
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .search_width(1)
+            .build()
+            .unwrap();
+        let mut events = Vec::new();
+        let jackpot = cracker
+            .crack_with(&CancellationToken::new(), |event: &Event| {
+                events.push(event.clone())
+            })
+            .unwrap();
+        assert!(matches!(events[0], Event::Started { tries: 0, .. }));
+        assert!(matches!(
+            events.last(),
+            Some(Event::Found { jackpot: found, stats }) if found.passphrase == jackpot.passphrase && stats.tries == 2
+        ));
+
+        // Wordlist lines that are not valid UTF-8 are reported as skipped.
+        let wordlist = TempFile::new("skipped-wordlist", b"foo\n\xff\n\n");
+        let skipping = builder()
+            .wordlist(wordlist.path())
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .search_width(1)
+            .build()
+            .unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        skipping
+            .crack_with(&CancellationToken::new(), sender)
+            .unwrap();
+        assert!(
+            receiver
+                .iter()
+                .any(|event| matches!(event, Event::Skipped { skipped: 1 }))
+        );
+
+        // Only the candidates tested up to the match are counted, here the first one, on 1 thread.
+        let foo = |wallet: &CandidateWallet| (wallet.passphrase() == "foo").then(Match::default);
+        let first = builder()
+            .candidates(candidates)
+            .matcher(foo)
+            .build()
+            .unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        pool.install(|| first.crack_with(&CancellationToken::new(), sender))
+            .unwrap();
+        assert!(matches!(
+            receiver.iter().last(),
+            Some(Event::Found { stats, .. }) if stats.tries == 1
+        ));
+
+        // A cancelled run stops before testing a batch, and checkpoints its session, whether the
+        // batch is full (on 1 thread) or the last one, cut short (on 64 threads).
+        for threads in [1, 64] {
+            let session = TempFile::new(&format!("cancel-{threads}"), "");
+            let cracker = builder()
+                .mask(Mask::new("?d?d?d?d", &[None, None, None, None]).unwrap())
+                .target_fingerprint(Fingerprint::from_str("00000000").unwrap())
+                .session(session.path())
+                .overwrite_session(true)
+                .build()
+                .unwrap();
+            let cancel = CancellationToken::new();
+            cancel.cancel();
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            assert!(matches!(
+                pool.install(|| cracker.crack_with(&cancel, sender)),
+                Err(HashsatError::Cancelled)
+            ));
+            let events: Vec<Event> = receiver.iter().collect();
+            assert!(matches!(
+                events.as_slice(),
+                [
+                    Event::Started { .. },
+                    Event::Checkpointed { tries: 0, .. },
+                    Event::Cancelled { .. }
+                ]
+            ));
+            assert!(!fs::read_to_string(session.path()).unwrap().is_empty());
+        }
     }
-}
 
-/// Print cracking parameters.
-fn print_cracking_params(wallet: &Wallet) {
-    println!("cracking");
-    println!(" {}", wallet.mnemonic);
-    println!("using alphabet");
-    println!(" {} ({})", wallet.alphabet, get_alphabet(&wallet.alphabet));
-    println!("with target address");
-    println!(" {}", wallet.target_address);
-    println!("on network");
-    println!(" {}", wallet.network);
-    println!("with search width of");
-    println!(
-        " {} addresses ({} external + {} internal)",
-        2 * wallet.search_width,
-        wallet.search_width,
-        wallet.search_width
-    );
-    println!("and passphrase length range of");
-    println!(
-        " ({},{})",
-        wallet.passphrase_length_range.0, wallet.passphrase_length_range.1
-    );
-    println!("using");
-    println!(" {} threads", rayon::current_num_threads());
-    println!();
-    std::thread::sleep(Duration::from_secs(1));
+    /// Non-ASCII candidates are NFKD-normalized before deriving their seed, as BIP39 does, from
+    /// any source.
+    #[test]
+    fn non_ascii_passphrase() {
+        let mnemonic = Mnemonic::from_str(TEST_MNEMONIC).unwrap();
+        let seed = mnemonic.to_seed_normalized("e\u{301}");
+        let fingerprint = SECP.with(|secp| {
+            Xpriv::new_master(Network::Bitcoin, &seed)
+                .unwrap()
+                .fingerprint(secp)
+        });
+        let cracker = || Cracker::builder(mnemonic.clone()).target_fingerprint(fingerprint);
+
+        // A precomposed `é` (U+00E9) from an alphabet.
+        let jackpot = cracker()
+            .alphabet(Alphabet::from_str("chars:aé").unwrap())
+            .passphrase_length_range(1, 1)
+            .build()
+            .unwrap()
+            .crack()
+            .unwrap();
+        assert_eq!(jackpot.passphrase, "\u{e9}");
+
+        // A precomposed `é` from a wordlist.
+        let wordlist = TempFile::new("nfkd-wordlist", "foo\n\u{e9}\n");
+        let jackpot = cracker()
+            .wordlist(wordlist.path())
+            .build()
+            .unwrap()
+            .crack()
+            .unwrap();
+        assert_eq!(jackpot.passphrase, "\u{e9}");
+    }
+
+    /// Plain `xpub`s don't imply a script type, so every standard derivation path is searched.
+    /// Account extended public key from BIP86.
+    #[test]
+    fn plain_account_xpub() {
+        let (xpub, script_type) = decode_xpub("xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ").unwrap();
+        assert_eq!(script_type, None);
+
+        let candidates = || Candidates(vec!["foo".to_string(), String::new()].into_iter());
+        let jackpot = builder()
+            .candidates(candidates)
+            .target_xpub(xpub, script_type)
+            .build()
+            .unwrap()
+            .crack()
+            .unwrap();
+        assert_eq!(jackpot.passphrase, "");
+        assert_eq!(
+            jackpot.derivation_path,
+            DerivationPath::from_str("m/86'/0'/0'").unwrap()
+        );
+    }
 }
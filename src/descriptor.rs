@@ -0,0 +1,393 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use core::fmt;
+use std::str::FromStr;
+
+use bitcoin::{
+    Address, AddressType, CompressedPublicKey, Network, Script, ScriptBuf,
+    bip32::{ChildNumber, DerivationPath, Fingerprint, Xpub},
+    hashes::{Hash, hash160},
+    key::{Secp256k1, TapTweak, Verification},
+};
+
+use crate::error::HashsatError;
+
+/// The single-key script types that can be derived from a candidate wallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScriptType {
+    /// Legacy P2PKH (BIP44), `pkh(...)`.
+    Pkh,
+    /// Nested SegWit P2SH-P2WPKH (BIP49), `sh(wpkh(...))`.
+    ShWpkh,
+    /// Native SegWit P2WPKH (BIP84), `wpkh(...)`.
+    Wpkh,
+    /// Taproot key path P2TR (BIP86), `tr(...)`.
+    Tr,
+}
+
+impl ScriptType {
+    /// All script types, in the order of their BIP43 purposes.
+    pub const ALL: [ScriptType; 4] = [
+        ScriptType::Pkh,
+        ScriptType::ShWpkh,
+        ScriptType::Wpkh,
+        ScriptType::Tr,
+    ];
+
+    /// The BIP43 purpose of the standard derivation path for the script type.
+    pub fn purpose(&self) -> u32 {
+        match self {
+            ScriptType::Pkh => 44,
+            ScriptType::ShWpkh => 49,
+            ScriptType::Wpkh => 84,
+            ScriptType::Tr => 86,
+        }
+    }
+
+    /// The type of the addresses of the script type.
+    pub fn address_type(&self) -> AddressType {
+        match self {
+            ScriptType::Pkh => AddressType::P2pkh,
+            ScriptType::ShWpkh => AddressType::P2sh,
+            ScriptType::Wpkh => AddressType::P2wpkh,
+            ScriptType::Tr => AddressType::P2tr,
+        }
+    }
+
+    /// The standard account derivation path for the script type, `m/purpose'/coin_type'/0'`.
+    pub fn standard_account_path(&self, coin_type: u32) -> DerivationPath {
+        DerivationPath::from(vec![
+            ChildNumber::Hardened {
+                index: self.purpose(),
+            },
+            ChildNumber::Hardened { index: coin_type },
+            ChildNumber::Hardened { index: 0 },
+        ])
+    }
+}
+
+impl TryFrom<AddressType> for ScriptType {
+    type Error = HashsatError;
+
+    /// P2SH addresses are assumed to be BIP49 nested SegWit (P2SH-P2WPKH), since that is the only
+    /// single-key script wallets put behind P2SH.
+    fn try_from(address_type: AddressType) -> Result<Self, Self::Error> {
+        match address_type {
+            AddressType::P2pkh => Ok(ScriptType::Pkh),
+            AddressType::P2sh => Ok(ScriptType::ShWpkh),
+            AddressType::P2wpkh => Ok(ScriptType::Wpkh),
+            AddressType::P2tr => Ok(ScriptType::Tr),
+            address_type => Err(HashsatError::UnsupportedAddressType(
+                address_type.to_string(),
+            )),
+        }
+    }
+}
+
+/// The payload of a single-key script pubkey, which is all that tells addresses apart.
+///
+/// Comparing payloads against a precomputed set of targets avoids building scripts or addresses
+/// for every derived key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Payload {
+    /// The hash160 of a public key, for P2PKH.
+    PubkeyHash([u8; 20]),
+    /// The hash160 of a P2WPKH redeem script, for P2SH-P2WPKH.
+    ScriptHash([u8; 20]),
+    /// The hash160 of a public key, for P2WPKH.
+    WPubkeyHash([u8; 20]),
+    /// The tweaked x-only output key, for P2TR.
+    OutputKey([u8; 32]),
+}
+
+impl Payload {
+    /// The payload of a script pubkey, if it is of a supported type.
+    pub(crate) fn from_script_pubkey(script_pubkey: &Script) -> Option<Self> {
+        let bytes = script_pubkey.as_bytes();
+        if script_pubkey.is_p2pkh() {
+            Some(Payload::PubkeyHash(bytes[3..23].try_into().ok()?))
+        } else if script_pubkey.is_p2sh() {
+            Some(Payload::ScriptHash(bytes[2..22].try_into().ok()?))
+        } else if script_pubkey.is_p2wpkh() {
+            Some(Payload::WPubkeyHash(bytes[2..22].try_into().ok()?))
+        } else if script_pubkey.is_p2tr() {
+            Some(Payload::OutputKey(bytes[2..34].try_into().ok()?))
+        } else {
+            None
+        }
+    }
+}
+
+/// A single-key output descriptor template, e.g. `wpkh([fp/84'/0'/0']/<0;1>/*)`.
+///
+/// The key of the template is replaced by the keys derived from each candidate wallet: addresses
+/// are derived at `account_path/keychain/index` for every keychain, and the script type decides
+/// how they are encoded. The key itself may be omitted or be any placeholder, and the origin
+/// fingerprint may be the `fp` placeholder.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Descriptor {
+    /// The script type.
+    pub script_type: ScriptType,
+    /// The master key fingerprint of the key origin, if known.
+    pub fingerprint: Option<Fingerprint>,
+    /// The derivation path of the key origin, i.e. of the account.
+    pub account_path: DerivationPath,
+    /// The keychains to derive addresses from (`0` is external, `1` is internal).
+    pub keychains: Vec<u32>,
+}
+
+impl Descriptor {
+    /// The descriptor of the script type at `account_path`, on `keychains`.
+    pub fn new(script_type: ScriptType, account_path: DerivationPath, keychains: Vec<u32>) -> Self {
+        Self {
+            script_type,
+            fingerprint: None,
+            account_path,
+            keychains,
+        }
+    }
+
+    /// The derivation path of the address at `index` on `keychain`.
+    pub fn derivation_path(&self, keychain: u32, index: u32) -> DerivationPath {
+        self.account_path
+            .child(ChildNumber::Normal { index: keychain })
+            .child(ChildNumber::Normal { index })
+    }
+
+    /// The script pubkey of the address of a child [`Xpub`].
+    ///
+    /// Taproot outputs follow BIP86: the child key is used as the internal key and tweaked with
+    /// an empty script tree.
+    pub fn script_pubkey<C: Verification>(&self, secp: &Secp256k1<C>, xpub: &Xpub) -> ScriptBuf {
+        let public_key = CompressedPublicKey(xpub.public_key);
+        match self.script_type {
+            ScriptType::Pkh => ScriptBuf::new_p2pkh(&public_key.pubkey_hash()),
+            ScriptType::ShWpkh => ScriptBuf::new_p2sh(
+                &ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash()).script_hash(),
+            ),
+            ScriptType::Wpkh => ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash()),
+            ScriptType::Tr => ScriptBuf::new_p2tr(secp, xpub.to_x_only_pub(), None),
+        }
+    }
+
+    /// The script pubkey payload of the address of a child [`Xpub`]. This is what derived keys
+    /// are matched by, so it only hashes what it has to.
+    pub(crate) fn payload<C: Verification>(&self, secp: &Secp256k1<C>, xpub: &Xpub) -> Payload {
+        match self.script_type {
+            ScriptType::Pkh => Payload::PubkeyHash(pubkey_hash(xpub)),
+            ScriptType::ShWpkh => {
+                // The P2WPKH redeem script: `OP_0 OP_PUSHBYTES_20 <pubkey hash>`.
+                let mut redeem_script = [0; 22];
+                redeem_script[1] = 20;
+                redeem_script[2..].copy_from_slice(&pubkey_hash(xpub));
+                Payload::ScriptHash(hash160::Hash::hash(&redeem_script).to_byte_array())
+            }
+            ScriptType::Wpkh => Payload::WPubkeyHash(pubkey_hash(xpub)),
+            ScriptType::Tr => {
+                let (output_key, _) = xpub.to_x_only_pub().tap_tweak(secp, None);
+                Payload::OutputKey(output_key.to_x_only_public_key().serialize())
+            }
+        }
+    }
+
+    /// The address of a child [`Xpub`].
+    pub fn address<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        xpub: &Xpub,
+        network: Network,
+    ) -> Address {
+        Address::from_script(&self.script_pubkey(secp, xpub), network)
+            .expect("single-key scripts have addresses")
+    }
+}
+
+/// The hash160 of the compressed public key of an [`Xpub`].
+fn pubkey_hash(xpub: &Xpub) -> [u8; 20] {
+    hash160::Hash::hash(&xpub.public_key.serialize()).to_byte_array()
+}
+
+impl FromStr for Descriptor {
+    type Err = HashsatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| HashsatError::InvalidDescriptor(s.to_string(), reason.to_string());
+
+        // Ignore the checksum, if any.
+        let descriptor = s
+            .split_once('#')
+            .map_or(s, |(descriptor, _)| descriptor)
+            .trim();
+
+        let (script_type, key) = [
+            (ScriptType::ShWpkh, "sh(wpkh(", "))"),
+            (ScriptType::Wpkh, "wpkh(", ")"),
+            (ScriptType::Pkh, "pkh(", ")"),
+            (ScriptType::Tr, "tr(", ")"),
+        ]
+        .into_iter()
+        .find_map(|(script_type, prefix, suffix)| {
+            descriptor
+                .strip_prefix(prefix)?
+                .strip_suffix(suffix)
+                .map(|key| (script_type, key))
+        })
+        .ok_or_else(|| invalid("expected `pkh(...)`, `sh(wpkh(...))`, `wpkh(...)` or `tr(...)`"))?;
+        if key.contains([',', '(', ')']) {
+            return Err(invalid("only single-key descriptors are supported"));
+        }
+
+        // Parse the key origin, if any.
+        let (fingerprint, account_path, key) = match key.strip_prefix('[') {
+            Some(key) => {
+                let (origin, key) = key
+                    .split_once(']')
+                    .ok_or_else(|| invalid("unterminated key origin"))?;
+                let (fingerprint, account_path) = origin.split_once('/').unwrap_or((origin, ""));
+                let fingerprint = match fingerprint {
+                    "fp" => None,
+                    fingerprint => Some(
+                        Fingerprint::from_str(fingerprint)
+                            .map_err(|_| invalid("invalid key origin fingerprint"))?,
+                    ),
+                };
+                let account_path = match account_path {
+                    "" => DerivationPath::master(),
+                    account_path => DerivationPath::from_str(&format!("m/{account_path}"))
+                        .map_err(|_| invalid("invalid key origin derivation path"))?,
+                };
+                (fingerprint, account_path, key)
+            }
+            None => (None, DerivationPath::master(), key),
+        };
+
+        // The key is a placeholder for the candidate keys, so only the steps after it matter.
+        let steps: Vec<&str> = key.split('/').skip(1).collect();
+        let [keychains, "*"] = steps[..] else {
+            return Err(invalid(
+                "expected the key to be followed by `/<0;1>/*` or `/0/*`",
+            ));
+        };
+        let keychains = keychains
+            .strip_prefix('<')
+            .and_then(|keychains| keychains.strip_suffix('>'))
+            .unwrap_or(keychains)
+            .split(';')
+            .map(u32::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid("invalid keychain"))?;
+        if keychains.iter().any(|&keychain| keychain >= 1 << 31) {
+            return Err(invalid("keychains can't be hardened"));
+        }
+
+        Ok(Self {
+            script_type,
+            fingerprint,
+            account_path,
+            keychains,
+        })
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, suffix) = match self.script_type {
+            ScriptType::Pkh => ("pkh(", ")"),
+            ScriptType::ShWpkh => ("sh(wpkh(", "))"),
+            ScriptType::Wpkh => ("wpkh(", ")"),
+            ScriptType::Tr => ("tr(", ")"),
+        };
+        let fingerprint = self
+            .fingerprint
+            .map_or("fp".to_string(), |fingerprint| fingerprint.to_string());
+        let keychains = match &self.keychains[..] {
+            [keychain] => keychain.to_string(),
+            keychains => format!(
+                "<{}>",
+                keychains
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+                    .join(";")
+            ),
+        };
+
+        write!(f, "{prefix}[{fingerprint}")?;
+        if !self.account_path.is_master() {
+            write!(f, "/{}", self.account_path)?;
+        }
+        write!(f, "]/{keychains}/*{suffix}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptor_templates() {
+        let descriptor = Descriptor::from_str("wpkh([fp/84'/0'/0']/<0;1>/*)").unwrap();
+        assert_eq!(descriptor.script_type, ScriptType::Wpkh);
+        assert_eq!(descriptor.fingerprint, None);
+        assert_eq!(
+            descriptor.account_path,
+            DerivationPath::from_str("m/84'/0'/0'").unwrap()
+        );
+        assert_eq!(descriptor.keychains, vec![0, 1]);
+        assert_eq!(descriptor.to_string(), "wpkh([fp/84'/0'/0']/<0;1>/*)");
+
+        // Keys and checksums are ignored, `h` hardened markers are accepted.
+        let descriptor = Descriptor::from_str("sh(wpkh([73c5da0a/49h/1h/0h]tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/0/*))#abcdefgh").unwrap();
+        assert_eq!(descriptor.script_type, ScriptType::ShWpkh);
+        assert_eq!(
+            descriptor.fingerprint,
+            Some(Fingerprint::from_str("73c5da0a").unwrap())
+        );
+        assert_eq!(descriptor.keychains, vec![0]);
+        assert_eq!(descriptor.to_string(), "sh(wpkh([73c5da0a/49'/1'/0']/0/*))");
+
+        assert_eq!(
+            Descriptor::from_str("tr([fp/86'/0'/0']/<0;1>/*)")
+                .unwrap()
+                .script_type,
+            ScriptType::Tr
+        );
+        assert_eq!(
+            Descriptor::from_str("pkh([fp/44'/0'/0']/<0;1>/*)")
+                .unwrap()
+                .script_type,
+            ScriptType::Pkh
+        );
+
+        for invalid in [
+            "wsh([fp/48'/0'/0'/2']/<0;1>/*)",
+            "tr([fp/86'/0'/0']/<0;1>/*,pk(KEY))",
+            "wpkh([fp/84'/0'/0']/<0;1>)",
+            "wpkh([fp/84'/0'/0']/0'/*)",
+            "wpkh([nope/84'/0'/0']/0/*)",
+        ] {
+            assert!(Descriptor::from_str(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    /// Payloads match the script pubkeys of the addresses they were derived for.
+    #[test]
+    fn payloads_match_script_pubkeys() {
+        let secp = Secp256k1::verification_only();
+        let xpub = Xpub::from_str("xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V").unwrap();
+
+        for script_type in [
+            ScriptType::Pkh,
+            ScriptType::ShWpkh,
+            ScriptType::Wpkh,
+            ScriptType::Tr,
+        ] {
+            let descriptor = Descriptor::new(script_type, DerivationPath::master(), vec![0]);
+            assert_eq!(
+                Payload::from_script_pubkey(&descriptor.script_pubkey(&secp, &xpub)),
+                Some(descriptor.payload(&secp, &xpub))
+            );
+        }
+    }
+}
@@ -6,25 +6,105 @@ use bitcoin::bip32;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-pub(crate) enum HashsatError {
+pub enum HashsatError {
     #[error("Invalid mnemonic: {0}")]
     InvalidMnemonic(#[from] bip39::Error),
 
     #[error("Invalid address: {0}")]
     InvalidAddress(#[from] bitcoin::address::ParseError),
 
+    #[error("Invalid extended public key {0}: {1}")]
+    InvalidXpub(String, String),
+
+    #[error("Invalid fingerprint: {0}")]
+    InvalidFingerprint(String),
+
     #[error("Invalid network: {0}")]
     InvalidNetwork(#[from] bitcoin::network::ParseNetworkError),
 
     #[error("Invalid derivation path: {0}")]
     InvalidDerivationPath(#[from] bip32::Error),
 
+    #[error("Invalid descriptor `{0}`: {1}")]
+    InvalidDescriptor(String, String),
+
+    #[error("Invalid alphabet `{0}`: {1}")]
+    InvalidAlphabet(String, String),
+
+    #[error("Invalid mask `{0}`: {1}")]
+    InvalidMask(String, String),
+
+    #[error("Invalid rule `{0}`: {1}")]
+    InvalidRule(String, String),
+
+    #[error("Rule file {0} has no rules")]
+    NoRules(String),
+
+    #[error("Invalid session file {0}: {1}")]
+    InvalidSession(String, String),
+
+    #[error("Session file {0} was created with a different configuration")]
+    SessionMismatch(String),
+
+    #[error(
+        "Session file {0} already exists. Restore it with `--restore`, or overwrite it with `--overwrite-session`"
+    )]
+    SessionExists(String),
+
+    #[error("Session file {0} can't be restored, as the candidates can't seek to where it stopped")]
+    UnseekableSession(String),
+
+    #[error(
+        "Invalid passphrase length range ({0},{1}): the minimum can't be greater than the maximum"
+    )]
+    InvalidLengthRange(usize, usize),
+
+    #[error("Invalid part {0}/{1}: the part number must be between 1 and the number of parts")]
+    InvalidPart(usize, usize),
+
+    #[error("Invalid coin type {0}: coin types must be below 2^31, as they are hardened")]
+    InvalidCoinType(u32),
+
+    #[error("Invalid keychain {0}: keychains can't be hardened")]
+    InvalidKeychain(u32),
+
+    #[error("Invalid search width {0}: addresses can't be derived at hardened indices, from 2^31")]
+    InvalidSearchWidth(usize),
+
+    #[error("No target address, xpub, fingerprint or matcher to match against")]
+    MissingTarget,
+
+    #[error(
+        "{0} candidates are too many to match against a 32-bit fingerprint alone, as a collision could come before the passphrase. Add a target address or xpub to confirm matches"
+    )]
+    UnconfirmedFingerprint(u128),
+
+    #[error(
+        "Candidates that can't be counted can't be matched against a 32-bit fingerprint alone, as a collision could come before the passphrase. Add a target address or xpub to confirm matches"
+    )]
+    UncountedFingerprint,
+
     #[error("Unsupported script type: {0}")]
     UnsupportedAddressType(String),
 
     #[error("Depleted search space of ({0},{1}) chars before finding any matches")]
     DepletedSearchSpace(usize, usize),
 
+    #[error("Depleted wordlist {0} before finding any matches")]
+    DepletedWordlist(String),
+
+    #[error("Depleted mask {0} before finding any matches")]
+    DepletedMask(String),
+
+    #[error("Depleted candidates before finding any matches")]
+    DepletedCandidates,
+
+    #[error("Cancelled before finding any matches")]
+    Cancelled,
+
+    #[error("Failed to spawn threads: {0}")]
+    ThreadPoolError(#[from] rayon::ThreadPoolBuildError),
+
     #[error("I/O error: {0}")]
     IOError(#[from] std::io::Error),
 }
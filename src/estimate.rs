@@ -0,0 +1,177 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::time::Duration;
+
+use crate::{
+    bench::measure, cracker::derive_wallet_and_assert_with, error::HashsatError,
+    pbkdf2::SeedDeriver, session::Session, source::candidates, types::Wallet,
+};
+
+/// How long the cracking rate is measured for.
+const CALIBRATION_DURATION: Duration = Duration::from_secs(1);
+
+/// A preflight estimate of how long it takes to exhaust the candidate space.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    /// The exact number of candidate passphrases, if it can be known in advance.
+    pub candidates: Option<u128>,
+    /// How many wallets per second are tested, as calibrated.
+    pub rate: f64,
+    /// How many candidates were already tested by the restored session, if any.
+    pub tried: usize,
+}
+
+impl Estimate {
+    /// Count the candidates of a wallet and calibrate the cracking rate on it.
+    pub(crate) fn new(wallet: &Wallet) -> Result<Self, HashsatError> {
+        Ok(Self {
+            candidates: keyspace(wallet)?,
+            rate: calibrate(wallet)?,
+            tried: Session::restore(wallet)?.map_or(0, |session| session.tries),
+        })
+    }
+
+    /// The time it takes to exhaust the candidates left to test, if they can be counted. It
+    /// saturates at [`Duration::MAX`] when it doesn't fit, or when no wallet was tested while
+    /// calibrating.
+    pub fn worst_case(&self) -> Option<Duration> {
+        let left = self.candidates?.saturating_sub(self.tried as u128);
+        if left == 0 {
+            return Some(Duration::ZERO);
+        }
+        Some(Duration::try_from_secs_f64(left as f64 / self.rate).unwrap_or(Duration::MAX))
+    }
+
+    /// The expected time to find the passphrase, if it is within the candidate space. Candidates
+    /// are walked in a scrambled order, so that is half of the worst case, which stays saturated
+    /// at [`Duration::MAX`].
+    pub fn expected(&self) -> Option<Duration> {
+        self.worst_case().map(|worst_case| match worst_case {
+            Duration::MAX => Duration::MAX,
+            worst_case => worst_case / 2,
+        })
+    }
+}
+
+/// The exact number of candidates a wallet will test, if its candidate source can tell. Partitioned
+/// runs only count the candidates of their part.
+fn keyspace(wallet: &Wallet) -> Result<Option<u128>, HashsatError> {
+    Ok(candidates(wallet, 0)?.keyspace())
+}
+
+/// The exact number of candidates a wallet will test, if it can be counted up front without reading
+/// a wordlist through, which only [`Estimate::new`] does.
+pub(crate) fn preflight_keyspace(wallet: &Wallet) -> Result<Option<u128>, HashsatError> {
+    match (&wallet.generator, &wallet.wordlist) {
+        (None, Some(_)) => Ok(None),
+        _ => keyspace(wallet),
+    }
+}
+
+/// Measure how many wallets per second are tested on all threads of the current thread pool, which
+/// the cracker runs on too, with the wallet's targets.
+fn calibrate(wallet: &Wallet) -> Result<f64, HashsatError> {
+    let seed_deriver = SeedDeriver::new(&wallet.mnemonic);
+    let passphrases = vec!["hashsat".to_string(); seed_deriver.lanes()];
+    let matcher = wallet.matcher()?;
+
+    Ok(measure(CALIBRATION_DURATION, |secp| {
+        let seeds = seed_deriver.seeds(&passphrases);
+        for (passphrase, seed) in passphrases.iter().zip(&seeds) {
+            derive_wallet_and_assert_with(secp, &matcher, wallet.network, passphrase, seed);
+        }
+        passphrases.len()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::bip32::Fingerprint;
+
+    use super::*;
+    use crate::{
+        alphabet::Alphabet,
+        builder::CrackerBuilder,
+        mask::Mask,
+        testing::{self, TempFile, wallet},
+    };
+
+    /// A builder for the test wallet, brute forcing numeric passphrases of 1 to 3 digits.
+    fn builder() -> CrackerBuilder {
+        testing::builder()
+            .target_fingerprint(Fingerprint::default())
+            .alphabet(Alphabet::from_str("numeric").unwrap())
+            .passphrase_length_range(1, 3)
+    }
+
+    #[test]
+    fn exact_keyspace() {
+        let keyspace = |builder: CrackerBuilder| keyspace(&wallet(builder)).unwrap();
+        assert_eq!(keyspace(builder()), Some(10 + 100 + 1000));
+
+        // Parts split the keyspace, with the remainder going to the first parts.
+        let parts = |n: usize| -> Vec<u128> {
+            (1..=n)
+                .map(|k| keyspace(builder().part(k, n)).unwrap())
+                .collect()
+        };
+        assert_eq!(parts(3), vec![370, 370, 370]);
+        assert_eq!(parts(4), vec![278, 278, 277, 277]);
+
+        let mask = Mask::new("?u?d?d", &Default::default()).unwrap();
+        assert_eq!(keyspace(builder().mask(mask)), Some(2600));
+
+        // Brute forcing alphanumeric passphrases of up to 10 characters.
+        let alphanumeric = || {
+            builder()
+                .alphabet(Alphabet::from_str("alphanumeric").unwrap())
+                .passphrase_length_range(1, 10)
+        };
+        assert_eq!(
+            keyspace(alphanumeric()),
+            Some((1..=10).map(|size| 62u128.pow(size)).sum())
+        );
+        assert_eq!(
+            preflight_keyspace(&wallet(alphanumeric())).unwrap(),
+            keyspace(alphanumeric())
+        );
+
+        // Wordlists are only counted for the estimate, as they are read through to count them.
+        let wordlist = TempFile::new("exact-keyspace", "foo\nbar\n");
+        let words = || builder().wordlist(wordlist.path());
+        assert_eq!(keyspace(words()), Some(2));
+        assert_eq!(preflight_keyspace(&wallet(words())).unwrap(), None);
+    }
+
+    #[test]
+    fn restored_estimate() {
+        let mut estimate = Estimate {
+            candidates: Some(1000),
+            rate: 100.0,
+            tried: 0,
+        };
+        assert_eq!(estimate.worst_case(), Some(Duration::from_secs(10)));
+        assert_eq!(estimate.expected(), Some(Duration::from_secs(5)));
+
+        // Only the candidates left after the restored session are counted.
+        estimate.tried = 600;
+        assert_eq!(estimate.worst_case(), Some(Duration::from_secs(4)));
+        assert_eq!(estimate.expected(), Some(Duration::from_secs(2)));
+        estimate.tried = 2000;
+        assert_eq!(estimate.worst_case(), Some(Duration::ZERO));
+
+        // Durations that don't fit, or can't be calibrated, saturate.
+        estimate.tried = 0;
+        estimate.rate = 0.0;
+        assert_eq!(estimate.worst_case(), Some(Duration::MAX));
+        estimate.candidates = Some(u128::MAX);
+        estimate.rate = 1.0;
+        assert_eq!(estimate.worst_case(), Some(Duration::MAX));
+        assert_eq!(estimate.expected(), Some(Duration::MAX));
+
+        estimate.candidates = None;
+        assert_eq!(estimate.worst_case(), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    time::Duration,
+};
+
+use crate::types::Jackpot;
+
+/// A token to cancel a cracking run from another thread.
+///
+/// Clones share the same state, so cancelling any of them cancels the run. The run stops after the
+/// batch of candidates in flight, and checkpoints its session, if any, so it can be restored.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that is not cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the run.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the run was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The statistics of a cracking run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    /// How many candidates were tested, including the ones of the restored session, if any.
+    pub tries: usize,
+    /// How long this run has been going for.
+    pub elapsed: Duration,
+    /// How many wallets per second this run tested.
+    pub rate: f64,
+}
+
+impl Stats {
+    /// The statistics of a run that tested `tries` candidates in `elapsed`, of which
+    /// `restored_tries` were tested by the restored session.
+    pub(crate) fn new(tries: usize, restored_tries: usize, elapsed: Duration) -> Self {
+        let rate = match elapsed.as_secs_f64() {
+            0.0 => 0.0,
+            secs => (tries - restored_tries) as f64 / secs,
+        };
+
+        Self {
+            tries,
+            elapsed,
+            rate,
+        }
+    }
+}
+
+/// Something that happened during a cracking run.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// The run started, with the seed used to scramble the alphabet, after `tries` candidates
+    /// were tested by the restored session, if any.
+    Started { seed: u64, tries: usize },
+    /// A batch of candidates was tested. `candidate` is a sample of the batch.
+    Progress { stats: Stats, candidate: String },
+    /// Candidates were skipped as they are not valid UTF-8, e.g. lines of a wordlist. `skipped` is
+    /// the total so far, reported whenever it grows.
+    Skipped { skipped: u128 },
+    /// Progress was checkpointed to the session file.
+    Checkpointed { path: PathBuf, tries: usize },
+    /// Progress could not be checkpointed to the session file. The run goes on.
+    CheckpointFailed { path: PathBuf, error: String },
+    /// The passphrase was found.
+    Found { jackpot: Box<Jackpot>, stats: Stats },
+    /// The candidate space was depleted without finding the passphrase.
+    Exhausted { stats: Stats },
+    /// The run was cancelled with a [`CancellationToken`].
+    Cancelled { stats: Stats },
+    /// The run stopped on an error of the candidate source, e.g. failing to read a wordlist.
+    Failed { error: String, stats: Stats },
+}
+
+/// A subscriber to the events of a cracking run.
+///
+/// Events are delivered on the thread that runs the cracker, in between batches of candidates, so
+/// subscribers should return quickly. Closures taking an [`Event`] are subscribers, and so are
+/// channel senders, to receive events on another thread.
+pub trait Subscriber {
+    /// Handle an event.
+    fn on_event(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event)> Subscriber for F {
+    fn on_event(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+impl Subscriber for Sender<Event> {
+    /// Send the event, unless the receiver is gone.
+    fn on_event(&mut self, event: &Event) {
+        let _ = self.send(event.clone());
+    }
+}
@@ -0,0 +1,483 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::time::Duration;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+
+use crate::{
+    bench::Measurement,
+    error::HashsatError,
+    estimate::Estimate,
+    events::{Event, Stats},
+    pbkdf2::Backend,
+    types::{Jackpot, Wallet},
+};
+
+/// The version of the JSON output format, bumped on breaking changes to the report, the events or
+/// the benchmark results.
+pub const JSON_VERSION: u32 = 1;
+
+/// Quote and escape a string as a JSON string.
+fn string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Format a count as a decimal JSON string, as counts like `u64` and `u128` can exceed the 2^53
+/// integers JSON numbers are exact up to in most parsers.
+fn count(n: impl ToString) -> String {
+    string(&n.to_string())
+}
+
+/// Format a value as JSON, or `null` if there is none.
+fn nullable<T>(value: Option<T>, format: impl FnOnce(T) -> String) -> String {
+    value.map_or("null".to_string(), format)
+}
+
+/// Format a derivation path with its `m/` prefix.
+fn derivation_path(path: &DerivationPath) -> String {
+    match path.is_master() {
+        true => string("m"),
+        false => string(&format!("m/{path}")),
+    }
+}
+
+/// Format the statistics of a run.
+fn stats(stats: &Stats) -> String {
+    format!(
+        r#"{{"tries":{},"elapsed_ms":{},"wallets_per_second":{:.1}}}"#,
+        count(stats.tries),
+        count(stats.elapsed.as_millis()),
+        stats.rate
+    )
+}
+
+/// Format the configuration of a wallet, with the seed the run used, if it started, and its
+/// mnemonic if `include_secrets`.
+fn config(wallet: &Wallet, seed: Option<u64>, include_secrets: bool) -> String {
+    let source = match (&wallet.generator, &wallet.wordlist, &wallet.mask) {
+        (Some(_), _, _) => r#"{"type":"custom"}"#.to_string(),
+        (_, Some(wordlist), _) => format!(
+            r#"{{"type":"wordlist","path":{}}}"#,
+            string(&wordlist.display().to_string())
+        ),
+        (_, _, Some(mask)) => format!(
+            r#"{{"type":"mask","mask":{},"candidates":{}}}"#,
+            string(&mask.to_string()),
+            count(mask.keyspace())
+        ),
+        _ => format!(
+            r#"{{"type":"alphabet","alphabet":{},"chars":{},"passphrase_length_range":[{},{}]}}"#,
+            string(&wallet.alphabet.to_string()),
+            string(&wallet.alphabet.chars().iter().collect::<String>()),
+            wallet.passphrase_length_range.0,
+            wallet.passphrase_length_range.1
+        ),
+    };
+    let list = |items: Vec<String>| {
+        items
+            .iter()
+            .map(|item| string(item))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    format!(
+        r#"{{{}"network":{},"source":{},"rules":{},"target_addresses":[{}],"target_xpub":{},"target_fingerprint":{},"custom_matcher":{},"descriptors":[{}],"search_width":{},"part":{},"seed":{},"session":{}}}"#,
+        match include_secrets {
+            true => format!(r#""mnemonic":{},"#, string(&wallet.mnemonic.to_string())),
+            false => String::new(),
+        },
+        string(&wallet.network.to_string()),
+        source,
+        nullable(wallet.rules.as_ref(), |rules| string(
+            &rules.display().to_string()
+        )),
+        list(
+            wallet
+                .target_addresses
+                .iter()
+                .map(|a| a.to_string())
+                .collect()
+        ),
+        nullable(wallet.target_xpub, |xpub| string(&xpub.to_string())),
+        nullable(wallet.target_fingerprint, |fingerprint| string(
+            &fingerprint.to_string()
+        )),
+        wallet.custom_matcher.is_some(),
+        list(wallet.descriptors.iter().map(|d| d.to_string()).collect()),
+        wallet.search_width,
+        nullable(wallet.part, |(k, n)| format!(r#"{{"k":{k},"n":{n}}}"#)),
+        nullable(seed.or(wallet.seed), count),
+        nullable(wallet.session.as_ref(), |session| string(
+            &session.display().to_string()
+        )),
+    )
+}
+
+/// Format the preflight estimate.
+fn estimate(estimate: &Estimate) -> String {
+    format!(
+        r#"{{"candidates":{},"tried":{},"wallets_per_second":{:.1},"worst_case_ms":{},"expected_ms":{}}}"#,
+        nullable(estimate.candidates, count),
+        count(estimate.tried),
+        estimate.rate,
+        nullable(estimate.worst_case(), |d| count(d.as_millis())),
+        nullable(estimate.expected(), |d| count(d.as_millis())),
+    )
+}
+
+/// Format the findings of a successful crack, with the master xpriv if `include_secrets`. The
+/// keychain and index are those of the matched address, if matched against addresses.
+fn jackpot(jackpot: &Jackpot, include_secrets: bool) -> String {
+    let (keychain, index) = match (jackpot.address.is_some(), jackpot.derivation_path.as_ref()) {
+        (
+            true,
+            [
+                ..,
+                ChildNumber::Normal { index: keychain },
+                ChildNumber::Normal { index },
+            ],
+        ) => (Some(*keychain), Some(*index)),
+        _ => (None, None),
+    };
+
+    format!(
+        r#"{{"passphrase":{},"xpub":{},{}"fingerprint":{},"address":{},"derivation_path":{},"keychain":{},"index":{}}}"#,
+        string(&jackpot.passphrase),
+        string(&jackpot.xpub.to_string()),
+        match include_secrets {
+            true => format!(r#""xpriv":{},"#, string(&jackpot.xpriv.to_string())),
+            false => String::new(),
+        },
+        string(&jackpot.xpub.fingerprint().to_string()),
+        nullable(jackpot.address.as_ref(), |address| string(
+            &address.to_string()
+        )),
+        derivation_path(&jackpot.derivation_path),
+        nullable(keychain, |keychain| keychain.to_string()),
+        nullable(index, |index| index.to_string()),
+    )
+}
+
+/// Format an event of a cracking run as a JSON line.
+pub fn event(event: &Event) -> String {
+    let fields = match event {
+        Event::Started { seed, tries } => {
+            format!(
+                r#""event":"started","seed":{},"tries":{}"#,
+                count(seed),
+                count(tries)
+            )
+        }
+        Event::Progress {
+            stats: s,
+            candidate,
+        } => format!(
+            r#""event":"progress","candidate":{},"stats":{}"#,
+            string(candidate),
+            stats(s)
+        ),
+        Event::Skipped { skipped } => {
+            format!(r#""event":"skipped","skipped":{}"#, count(skipped))
+        }
+        Event::Checkpointed { path, tries } => format!(
+            r#""event":"checkpointed","path":{},"tries":{}"#,
+            string(&path.display().to_string()),
+            count(tries)
+        ),
+        Event::CheckpointFailed { path, error } => format!(
+            r#""event":"checkpoint_failed","path":{},"error":{}"#,
+            string(&path.display().to_string()),
+            string(error)
+        ),
+        Event::Found { jackpot, stats: s } => format!(
+            r#""event":"found","passphrase":{},"stats":{}"#,
+            string(&jackpot.passphrase),
+            stats(s)
+        ),
+        Event::Exhausted { stats: s } => format!(r#""event":"exhausted","stats":{}"#, stats(s)),
+        Event::Cancelled { stats: s } => format!(r#""event":"cancelled","stats":{}"#, stats(s)),
+        Event::Failed { error, stats: s } => format!(
+            r#""event":"failed","error":{},"stats":{}"#,
+            string(error),
+            stats(s)
+        ),
+    };
+
+    format!(r#"{{"version":{JSON_VERSION},{fields}}}"#)
+}
+
+/// Format the final report of a run: its status, configuration, estimate, findings and stats.
+///
+/// `result` is the jackpot of a successful crack, `None` if only estimating, or the error the run
+/// stopped with. `wallet` is `None` if the configuration is invalid. `seed` is the seed of the
+/// [`Event::Started`] event, if the run started, as a restored run takes it from its session
+/// instead of the configuration. The mnemonic and the master xpriv are left out unless
+/// `include_secrets`, as reports tend to end up in logs.
+pub fn report(
+    wallet: Option<&Wallet>,
+    estimate_res: Option<&Estimate>,
+    result: &Result<Option<Jackpot>, HashsatError>,
+    stats_res: Option<Stats>,
+    seed: Option<u64>,
+    include_secrets: bool,
+) -> String {
+    let status = match result {
+        Ok(Some(_)) => "found",
+        Ok(None) => "estimated",
+        Err(HashsatError::Cancelled) => "cancelled",
+        Err(
+            HashsatError::DepletedCandidates
+            | HashsatError::DepletedWordlist(_)
+            | HashsatError::DepletedMask(_)
+            | HashsatError::DepletedSearchSpace(..),
+        ) => "exhausted",
+        Err(_) => "error",
+    };
+
+    format!(
+        r#"{{"version":{JSON_VERSION},"status":"{status}","exit_reason":{},"config":{},"estimate":{},"jackpot":{},"stats":{}}}"#,
+        nullable(result.as_ref().err(), |e| string(&e.to_string())),
+        nullable(wallet, |wallet| config(wallet, seed, include_secrets)),
+        nullable(estimate_res, estimate),
+        nullable(result.as_ref().ok().and_then(Option::as_ref), |found| {
+            jackpot(found, include_secrets)
+        }),
+        nullable(stats_res.as_ref(), stats),
+    )
+}
+
+/// Format the results of a benchmark: the seed derivation backend, how long each stage was
+/// measured for, and the rate of each stage on each thread count.
+pub fn bench(backend: Backend, duration: Duration, measurements: &[Measurement]) -> String {
+    let results = measurements
+        .iter()
+        .map(|measurement| {
+            format!(
+                r#"{{"stage":{},"address_type":{},"threads":{},"wallets_per_second":{:.1}}}"#,
+                string(measurement.stage.name()),
+                nullable(measurement.stage.script_type(), |script_type| string(
+                    &script_type.address_type().to_string()
+                )),
+                measurement.threads,
+                measurement.rate,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!(
+        r#"{{"version":{JSON_VERSION},"backend":{},"duration_ms":{},"results":[{results}]}}"#,
+        string(&backend.to_string()),
+        count(duration.as_millis()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, str::FromStr, time::Duration};
+
+    use bitcoin::Address;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{bench::Stage, descriptor::ScriptType, testing::builder};
+
+    #[test]
+    fn escaping() {
+        assert_eq!(string("abc"), r#""abc""#);
+        assert_eq!(string("a\"b\\c\nd\u{1}é"), r#""a\"b\\c\nd\u0001é""#);
+    }
+
+    #[test]
+    fn found_report() {
+        let cracker = builder()
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .passphrase_length_range(0, 0)
+            .search_width(1)
+            .build()
+            .unwrap();
+        let found = cracker.crack().map(Some);
+        let stats = Stats::new(1, 0, Duration::from_secs(1));
+        let report = report(
+            Some(cracker.wallet()),
+            None,
+            &found,
+            Some(stats),
+            None,
+            false,
+        );
+
+        assert!(report.starts_with(
+            r#"{"version":1,"status":"found","exit_reason":null,"config":{"network":"bitcoin","#
+        ));
+        assert!(report.contains(r#""address":"bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu","derivation_path":"m/84'/0'/0'/0/0","keychain":0,"index":0}"#));
+        assert!(
+            report.ends_with(
+                r#""stats":{"tries":"1","elapsed_ms":"1000","wallets_per_second":1.0}}"#
+            )
+        );
+        assert!(!report.contains("abandon") && !report.contains("xprv"));
+        assert!(report.contains(r#""seed":null"#));
+
+        // The seed the run started with is reported, e.g. from a restored session.
+        let report = super::report(
+            Some(cracker.wallet()),
+            None,
+            &found,
+            Some(stats),
+            Some(3),
+            false,
+        );
+        assert!(report.contains(r#""seed":"3""#));
+
+        // Secrets are only included when asked for.
+        let report = super::report(
+            Some(cracker.wallet()),
+            None,
+            &found,
+            Some(stats),
+            None,
+            true,
+        );
+        let report: Value = serde_json::from_str(&report).unwrap();
+        assert!(
+            report["config"]["mnemonic"]
+                .as_str()
+                .unwrap()
+                .starts_with("abandon")
+        );
+        assert!(
+            report["jackpot"]["xpriv"]
+                .as_str()
+                .unwrap()
+                .starts_with("xprv")
+        );
+
+        let report = super::report(None, None, &Err(HashsatError::Cancelled), None, None, false);
+        assert_eq!(
+            report,
+            r#"{"version":1,"status":"cancelled","exit_reason":"Cancelled before finding any matches","config":null,"estimate":null,"jackpot":null,"stats":null}"#
+        );
+    }
+
+    #[test]
+    fn bench_results() {
+        let measurements = [
+            Measurement {
+                stage: Stage::Pbkdf2,
+                threads: 1,
+                rate: 4289.04,
+            },
+            Measurement {
+                stage: Stage::Total(ScriptType::Tr),
+                threads: 8,
+                rate: 30000.0,
+            },
+        ];
+
+        assert_eq!(
+            bench(Backend::Avx2, Duration::from_millis(1500), &measurements),
+            r#"{"version":1,"backend":"avx2","duration_ms":"1500","results":[{"stage":"pbkdf2","address_type":null,"threads":1,"wallets_per_second":4289.0},{"stage":"total","address_type":"p2tr","threads":8,"wallets_per_second":30000.0}]}"#
+        );
+        assert_eq!(
+            bench(Backend::Scalar, Duration::from_secs(1), &[]),
+            r#"{"version":1,"backend":"scalar","duration_ms":"1000","results":[]}"#
+        );
+    }
+
+    #[test]
+    fn events() {
+        let cracker = builder()
+            .target_address(
+                Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").unwrap(),
+            )
+            .passphrase_length_range(0, 0)
+            .search_width(1)
+            .build()
+            .unwrap();
+        let jackpot = Box::new(cracker.crack().unwrap());
+        // Counts past 2^53 would lose precision as JSON numbers.
+        let stats = Stats::new(usize::MAX, 0, Duration::from_secs(u64::MAX));
+        let path = PathBuf::from("session \"1\".json");
+
+        let events = [
+            Event::Started {
+                seed: u64::MAX,
+                tries: usize::MAX,
+            },
+            Event::Progress {
+                stats,
+                candidate: "a\nb".to_string(),
+            },
+            Event::Checkpointed {
+                path: path.clone(),
+                tries: usize::MAX,
+            },
+            Event::CheckpointFailed {
+                path,
+                error: "disk full".to_string(),
+            },
+            Event::Found { jackpot, stats },
+            Event::Exhausted { stats },
+            Event::Cancelled { stats },
+            Event::Failed {
+                error: "unreadable".to_string(),
+                stats,
+            },
+            Event::Skipped { skipped: u128::MAX },
+        ];
+        let expected_stats = serde_json::json!({
+            "tries": usize::MAX.to_string(),
+            "elapsed_ms": (u64::MAX as u128 * 1000).to_string(),
+            "wallets_per_second": 1.0,
+        });
+
+        let parsed: Vec<Value> = events
+            .iter()
+            .map(|e| serde_json::from_str(&event(e)).unwrap())
+            .collect();
+        for parsed in &parsed {
+            assert_eq!(parsed["version"], JSON_VERSION);
+        }
+        assert_eq!(parsed[0]["event"], "started");
+        assert_eq!(parsed[0]["seed"], u64::MAX.to_string());
+        assert_eq!(parsed[0]["tries"], usize::MAX.to_string());
+        assert_eq!(parsed[1]["event"], "progress");
+        assert_eq!(parsed[1]["candidate"], "a\nb");
+        assert_eq!(parsed[1]["stats"], expected_stats);
+        assert_eq!(parsed[2]["event"], "checkpointed");
+        assert_eq!(parsed[2]["path"], "session \"1\".json");
+        assert_eq!(parsed[2]["tries"], usize::MAX.to_string());
+        assert_eq!(parsed[3]["event"], "checkpoint_failed");
+        assert_eq!(parsed[3]["error"], "disk full");
+        assert_eq!(parsed[4]["event"], "found");
+        assert_eq!(parsed[4]["passphrase"], "");
+        assert_eq!(parsed[4]["stats"], expected_stats);
+        for (parsed, name) in parsed[5..7].iter().zip(["exhausted", "cancelled"]) {
+            assert_eq!(parsed["event"], name);
+            assert_eq!(parsed["stats"], expected_stats);
+        }
+        assert_eq!(parsed[7]["event"], "failed");
+        assert_eq!(parsed[7]["error"], "unreadable");
+        assert_eq!(parsed[7]["stats"], expected_stats);
+        assert_eq!(parsed[8]["event"], "skipped");
+        assert_eq!(parsed[8]["skipped"], u128::MAX.to_string());
+    }
+}
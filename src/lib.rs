@@ -0,0 +1,48 @@
+//! hashsat: a bitcoin passphrase cracker
+//!
+//! Configure a [`Cracker`] for the mnemonic of a wallet with a [`CrackerBuilder`], with what is
+//! known about the wallet (its addresses, account xpub, master key fingerprint or any
+//! [`Matcher`]) and how to generate candidate passphrases (an [`Alphabet`], a wordlist, a
+//! [`Mask`] or any [`CandidateSource`]), then crack it for a [`Jackpot`].
+
+mod alphabet;
+mod bench;
+mod brute_force;
+mod builder;
+mod cracker;
+mod descriptor;
+mod error;
+mod estimate;
+mod events;
+pub mod json;
+mod mask;
+pub mod matcher;
+mod partition;
+mod pbkdf2;
+mod rules;
+mod session;
+mod slip132;
+mod source;
+#[cfg(any(test, feature = "testing"))]
+#[doc(hidden)]
+pub mod testing;
+mod types;
+mod wordlist;
+
+pub use bip39;
+pub use bitcoin;
+
+pub use crate::alphabet::Alphabet;
+pub use crate::bench::{Measurement, Stage, bench, default_bench_threads};
+pub use crate::builder::CrackerBuilder;
+pub use crate::cracker::Cracker;
+pub use crate::descriptor::{Descriptor, ScriptType};
+pub use crate::error::HashsatError;
+pub use crate::estimate::Estimate;
+pub use crate::events::{CancellationToken, Event, Stats, Subscriber};
+pub use crate::mask::Mask;
+pub use crate::matcher::Matcher;
+pub use crate::pbkdf2::Backend;
+pub use crate::slip132::decode_xpub;
+pub use crate::source::CandidateSource;
+pub use crate::types::{Jackpot, Wallet};
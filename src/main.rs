@@ -1,27 +1,146 @@
 //! hashsat: a bitcoin passphrase cracker
 
+use std::{thread, time::Duration};
+
 use clap::Parser;
-use error::HashsatError;
+use hashsat::{
+    Backend, CancellationToken, Cracker, Event, HashsatError, Stats, Subscriber, bench,
+    default_bench_threads,
+    json::{self, report},
+};
 
-pub(crate) mod cli;
-pub(crate) mod cracker;
-pub(crate) mod error;
-pub(crate) mod types;
+mod cli;
+mod terminal;
 
-use crate::cli::{Arguments, parse_cli_arguments};
-use crate::cracker::crack;
-use crate::types::Wallet;
+use crate::{
+    cli::{Arguments, BenchArguments, Command, parse_cli_arguments},
+    terminal::{
+        Renderer, print_bench_header, print_cracking_params, print_estimate, print_jackpot,
+        print_keyspace, print_measurement,
+    },
+};
 
-fn main() -> Result<(), HashsatError> {
+fn main() {
     let args: Arguments = Arguments::parse();
-    let mut wallet: Wallet = parse_cli_arguments(args)?;
-
-    // crack 'em up!
-    match crack(&mut wallet) {
-        Ok(()) => Ok(()),
-        Err(e) => {
-            eprintln!("\n\nerr: {e}\n");
-            std::process::exit(1)
+    let estimate_only = args.estimate;
+    let include_secrets = args.include_secrets;
+    let res = match args.command {
+        Some(Command::Bench(args)) => run_bench(args),
+        None if args.output == "json" => run_json(args, estimate_only, include_secrets),
+        // crack 'em up!
+        None => parse_cli_arguments(args).and_then(|cracker| run(&cracker, estimate_only)),
+    };
+
+    if let Err(e) = res {
+        eprintln!("\n\nerr: {e}\n");
+        std::process::exit(1)
+    }
+}
+
+/// Print the cracking parameters, then print the preflight estimate if only estimating, or the
+/// size of the candidate space and crack the passphrase otherwise. The preflight calibrates the
+/// cracking rate and reads wordlists through to count them, so it only runs when asked for.
+fn run(cracker: &Cracker, estimate_only: bool) -> Result<(), HashsatError> {
+    println!("\nspinning up hashers...\n");
+    thread::sleep(Duration::from_secs(1));
+    print_cracking_params(cracker.wallet());
+    if estimate_only {
+        print_estimate(&cracker.estimate()?);
+        return Ok(());
+    }
+    print_keyspace(cracker.wallet(), cracker.keyspace()?);
+
+    let jackpot = cracker.crack_with(&cancel_on_ctrl_c(), Renderer::default())?;
+    print_jackpot(cracker.wallet(), &jackpot);
+
+    Ok(())
+}
+
+/// Like [`run`], but print a JSON report on stdout and JSON lines events on stderr, then exit
+/// with `0` if the passphrase was found or only estimating, or `1` otherwise. The report only has
+/// an estimate when only estimating, and only has the mnemonic and xpriv with `include_secrets`.
+fn run_json(args: Arguments, estimate_only: bool, include_secrets: bool) -> ! {
+    let mut events = JsonLines::default();
+    let (wallet, estimate, result) = match parse_cli_arguments(args) {
+        Ok(cracker) if estimate_only => match cracker.estimate() {
+            Ok(estimate) => (Some(cracker), Some(estimate), Ok(None)),
+            Err(e) => (Some(cracker), None, Err(e)),
+        },
+        Ok(cracker) => {
+            let result = cracker
+                .crack_with(&cancel_on_ctrl_c(), |event: &Event| events.on_event(event))
+                .map(Some);
+            (Some(cracker), None, result)
+        }
+        Err(e) => (None, None, Err(e)),
+    };
+
+    println!(
+        "{}",
+        report(
+            wallet.as_ref().map(Cracker::wallet),
+            estimate.as_ref(),
+            &result,
+            events.stats,
+            events.seed,
+            include_secrets
+        )
+    );
+    std::process::exit(if result.is_ok() { 0 } else { 1 })
+}
+
+/// A token cancelled on Ctrl-C, so the run stops between batches and reports its stats, instead of
+/// being killed with the cursor hidden and its session not checkpointed.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    if let Err(e) = ctrlc::set_handler(move || handler_token.cancel()) {
+        eprintln!("warn: failed to handle Ctrl-C: {e}");
+    }
+    token
+}
+
+/// Benchmark seed derivation, printing the results as a table or as a JSON object.
+fn run_bench(args: BenchArguments) -> Result<(), HashsatError> {
+    let backend = Backend::detect();
+    let threads = args.threads.unwrap_or_else(default_bench_threads);
+
+    if args.output == "json" {
+        let measurements = bench(backend, &threads, args.duration, |_| {})?;
+        println!("{}", json::bench(backend, args.duration, &measurements));
+        return Ok(());
+    }
+
+    print_bench_header(backend, args.duration, &threads);
+    let mut column = 0;
+    bench(backend, &threads, args.duration, |measurement| {
+        print_measurement(measurement, column, threads.len());
+        column = (column + 1) % threads.len();
+    })?;
+    println!();
+
+    Ok(())
+}
+
+/// Writes the events of a cracking run to stderr as JSON lines, and keeps the seed it started with
+/// and the latest stats for the report.
+#[derive(Debug, Default)]
+struct JsonLines {
+    seed: Option<u64>,
+    stats: Option<Stats>,
+}
+
+impl Subscriber for JsonLines {
+    fn on_event(&mut self, e: &Event) {
+        match e {
+            Event::Started { seed, .. } => self.seed = Some(*seed),
+            Event::Progress { stats, .. }
+            | Event::Found { stats, .. }
+            | Event::Exhausted { stats }
+            | Event::Cancelled { stats }
+            | Event::Failed { stats, .. } => self.stats = Some(*stats),
+            _ => {}
         }
+        eprintln!("{}", json::event(e));
     }
 }
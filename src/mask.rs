@@ -0,0 +1,261 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use core::fmt;
+
+use crate::{
+    alphabet::{ALPHABET_LOWERCASE, ALPHABET_NUMERIC, ALPHABET_SYMBOLS, ALPHABET_UPPERCASE},
+    error::HashsatError,
+    source::CandidateSource,
+};
+
+/// A hashcat-style mask, where every position draws from its own charset.
+///
+/// The built-in charsets are `?l` (lowercase), `?u` (uppercase), `?d` (digits), `?s` (symbols),
+/// `?a` (all of the above), `?h`/`?H` (lower/uppercase hex) and `??` (a literal `?`). The custom
+/// charsets `?1` to `?4` can be defined by the user, and may themselves reference the built-in
+/// charsets. Any other character is a literal.
+#[derive(Clone, Debug)]
+pub struct Mask {
+    /// The mask, as given by the user.
+    mask: String,
+    /// The charset for each position of the mask.
+    positions: Vec<Vec<char>>,
+}
+
+impl Mask {
+    /// Parse a mask, with up to four custom charsets for `?1` to `?4`.
+    pub fn new(mask: &str, custom_charsets: &[Option<String>; 4]) -> Result<Self, HashsatError> {
+        let invalid = |reason: String| HashsatError::InvalidMask(mask.to_string(), reason);
+
+        let custom_charsets = custom_charsets
+            .iter()
+            .map(|charset| {
+                charset
+                    .as_deref()
+                    .map(|charset| expand(charset).map_err(invalid))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut positions = Vec::new();
+        let mut chars = mask.chars();
+        while let Some(c) = chars.next() {
+            if c != '?' {
+                positions.push(vec![c]);
+                continue;
+            }
+            let class = chars
+                .next()
+                .ok_or_else(|| invalid("dangling `?` at the end of the mask".to_string()))?;
+            let charset = match class {
+                '1'..='4' => custom_charsets[class as usize - '1' as usize]
+                    .clone()
+                    .ok_or_else(|| invalid(format!("custom charset `?{class}` is not defined")))?,
+                _ => {
+                    builtin(class).ok_or_else(|| invalid(format!("unknown charset `?{class}`")))?
+                }
+            };
+            positions.push(charset);
+        }
+        // The candidates are counted and indexed with a `u128`.
+        positions
+            .iter()
+            .try_fold(1u128, |acc, charset| acc.checked_mul(charset.len() as u128))
+            .ok_or_else(|| invalid("too many candidates".to_string()))?;
+
+        Ok(Self {
+            mask: mask.to_string(),
+            positions,
+        })
+    }
+
+    /// The charset of each position of the mask.
+    pub(crate) fn charsets(&self) -> &[Vec<char>] {
+        &self.positions
+    }
+
+    /// The number of candidate passphrases this mask generates, which was checked not to overflow
+    /// on parsing.
+    pub fn keyspace(&self) -> u128 {
+        self.positions
+            .iter()
+            .map(|charset| charset.len() as u128)
+            .product()
+    }
+
+    /// Iterate over all candidate passphrases of this mask.
+    pub(crate) fn iter(&self) -> MaskIter {
+        MaskIter {
+            positions: self.positions.clone(),
+            total: self.keyspace(),
+            n: 0,
+        }
+    }
+}
+
+/// Iterator over the candidate passphrases of a [`Mask`].
+///
+/// Candidates are generated with a mixed-radix variant of the `Radix Conversion` algorithm,
+/// where each position has the radix of its own charset.
+pub(crate) struct MaskIter {
+    /// The charset for each position of the mask.
+    positions: Vec<Vec<char>>,
+    /// The number of candidate passphrases.
+    total: u128,
+    /// The index of the next candidate passphrase.
+    n: u128,
+}
+
+impl Iterator for MaskIter {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.n >= self.total {
+            return None;
+        }
+
+        let mut n = self.n;
+        let mut result = String::new();
+        for charset in &self.positions {
+            result.push(charset[(n % charset.len() as u128) as usize]);
+            n /= charset.len() as u128;
+        }
+        self.n += 1;
+
+        Some(result)
+    }
+}
+
+impl CandidateSource for MaskIter {
+    fn keyspace(&self) -> Option<u128> {
+        Some(self.total)
+    }
+
+    fn position(&self) -> Vec<u128> {
+        vec![self.n]
+    }
+
+    fn seek(&mut self, position: &[u128]) -> bool {
+        match position {
+            [n] => {
+                self.n = *n;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mask)
+    }
+}
+
+/// Get the characters of a built-in charset from its identifier.
+fn builtin(class: char) -> Option<Vec<char>> {
+    let charset = match class {
+        'l' => ALPHABET_LOWERCASE.to_string(),
+        'u' => ALPHABET_UPPERCASE.to_string(),
+        'd' => ALPHABET_NUMERIC.to_string(),
+        's' => ALPHABET_SYMBOLS.to_string(),
+        'a' => [
+            ALPHABET_LOWERCASE,
+            ALPHABET_UPPERCASE,
+            ALPHABET_NUMERIC,
+            ALPHABET_SYMBOLS,
+        ]
+        .concat(),
+        'h' => "0123456789abcdef".to_string(),
+        'H' => "0123456789ABCDEF".to_string(),
+        '?' => "?".to_string(),
+        _ => return None,
+    };
+
+    Some(charset.chars().collect())
+}
+
+/// Expand a custom charset definition, which may reference built-in charsets, into its
+/// deduplicated characters.
+fn expand(definition: &str) -> Result<Vec<char>, String> {
+    let mut charset: Vec<char> = Vec::new();
+    let mut chars = definition.chars();
+    while let Some(c) = chars.next() {
+        let expanded = if c == '?' {
+            let class = chars
+                .next()
+                .ok_or_else(|| format!("dangling `?` in custom charset `{definition}`"))?;
+            builtin(class).ok_or_else(|| format!("unknown charset `?{class}` in `{definition}`"))?
+        } else {
+            vec![c]
+        };
+        for c in expanded {
+            if !charset.contains(&c) {
+                charset.push(c);
+            }
+        }
+    }
+    if charset.is_empty() {
+        return Err("empty custom charset".to_string());
+    }
+
+    Ok(charset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(mask: &str, custom_charsets: [Option<&str>; 4]) -> Result<Mask, HashsatError> {
+        Mask::new(mask, &custom_charsets.map(|c| c.map(str::to_string)))
+    }
+
+    #[test]
+    fn parse_masks() {
+        let no_charsets = [None; 4];
+
+        // Built-in charsets and literals.
+        let parsed = mask("a?l?u?d?s?a?h?H", no_charsets).unwrap();
+        let lengths: Vec<usize> = parsed.charsets().iter().map(Vec::len).collect();
+        assert_eq!(lengths, [1, 26, 26, 10, 33, 95, 16, 16]);
+        assert_eq!(parsed.keyspace(), 26 * 26 * 10 * 33 * 95 * 16 * 16);
+
+        // `??` is a literal `?`.
+        let candidates: Vec<String> = mask("??a?d", no_charsets).unwrap().iter().collect();
+        assert_eq!(candidates.len(), 10);
+        assert_eq!(candidates[0], "?a0");
+
+        // Custom charsets may reference built-in ones, and are deduplicated.
+        let parsed = mask(
+            "?1?2?3?4",
+            [Some("ab"), Some("?dx"), Some("aa"), Some("?h?d")],
+        )
+        .unwrap();
+        let lengths: Vec<usize> = parsed.charsets().iter().map(Vec::len).collect();
+        assert_eq!(lengths, [2, 11, 1, 16]);
+
+        assert!(mask("?1", no_charsets).is_err());
+        assert!(mask("?x", no_charsets).is_err());
+        assert!(mask("abc?", no_charsets).is_err());
+        assert!(mask("?1", [Some("?"), None, None, None]).is_err());
+        // Too many candidates to count.
+        assert!(mask(&"?a".repeat(19), no_charsets).is_ok());
+        assert!(mask(&"?a".repeat(20), no_charsets).is_err());
+    }
+
+    #[test]
+    fn seek_and_position() {
+        let parsed = mask("?d?1", [Some("xyz"), None, None, None]).unwrap();
+        let full: Vec<String> = parsed.iter().collect();
+        assert_eq!(full.len() as u128, parsed.keyspace());
+
+        for stop in 0..=full.len() {
+            let mut iter = parsed.iter();
+            let mut candidates: Vec<String> = iter.by_ref().take(stop).collect();
+            let mut resumed = parsed.iter();
+            assert!(resumed.seek(&iter.position()));
+            candidates.extend(resumed);
+            assert_eq!(candidates, full);
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use core::fmt;
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::HashSet,
+    sync::Arc,
+};
+
+use bitcoin::{
+    Address, Network, NetworkKind,
+    bip32::{ChildNumber, DerivationPath, Fingerprint, Xpriv, Xpub},
+    key::Secp256k1,
+    secp256k1,
+};
+
+use crate::{
+    descriptor::{Descriptor, Payload},
+    error::HashsatError,
+};
+
+/// A wallet derived from a candidate passphrase, to be tested by a [`Matcher`].
+///
+/// Keys are derived on demand, and the master public key and the hardened keys above accounts only
+/// once, so matchers only pay for the keys they look at, and accounts sharing a path prefix, e.g.
+/// `m/84'/0'`, share its derivation.
+pub struct CandidateWallet<'a> {
+    /// The `secp256k1` context of the worker thread.
+    secp: &'a Secp256k1<secp256k1::All>,
+    /// The candidate passphrase.
+    passphrase: &'a str,
+    /// The BIP39 seed of the mnemonic and the candidate passphrase.
+    seed: &'a [u8; 64],
+    /// The master extended private key of the seed.
+    master_xpriv: Xpriv,
+    /// The master extended public key, computed on first use.
+    master_xpub: OnceCell<Xpub>,
+    /// The extended private keys derived so far, by derivation path.
+    derived: RefCell<Vec<(DerivationPath, Xpriv)>>,
+}
+
+impl<'a> CandidateWallet<'a> {
+    /// Create the master key of a seed derived from a candidate passphrase.
+    pub fn new(
+        secp: &'a Secp256k1<secp256k1::All>,
+        passphrase: &'a str,
+        seed: &'a [u8; 64],
+        network: Network,
+    ) -> Self {
+        Self {
+            secp,
+            passphrase,
+            seed,
+            master_xpriv: Xpriv::new_master(NetworkKind::from(network), seed).unwrap(),
+            master_xpub: OnceCell::new(),
+            derived: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The `secp256k1` context to derive keys with.
+    pub fn secp(&self) -> &Secp256k1<secp256k1::All> {
+        self.secp
+    }
+
+    /// The candidate passphrase.
+    pub fn passphrase(&self) -> &str {
+        self.passphrase
+    }
+
+    /// The BIP39 seed of the mnemonic and the candidate passphrase.
+    pub fn seed(&self) -> &[u8; 64] {
+        self.seed
+    }
+
+    /// The master extended private key.
+    pub fn master_xpriv(&self) -> &Xpriv {
+        &self.master_xpriv
+    }
+
+    /// The master extended public key.
+    pub fn master_xpub(&self) -> &Xpub {
+        self.master_xpub
+            .get_or_init(|| Xpub::from_priv(self.secp, &self.master_xpriv))
+    }
+
+    /// The master key fingerprint.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.master_xpub().fingerprint()
+    }
+
+    /// The extended public key of an account. Account paths are hardened, so the account key is
+    /// derived privately, but everything below it can be derived publicly from its xpub.
+    ///
+    /// The account key is derived from the deepest key already derived on its path, and every key
+    /// on the way is kept for the next accounts.
+    pub fn account_xpub(&self, account_path: &DerivationPath) -> Xpub {
+        let mut derived = self.derived.borrow_mut();
+        let children = account_path.as_ref();
+        let (depth, mut xpriv) = derived
+            .iter()
+            .filter(|(path, _)| children.starts_with(path.as_ref()))
+            .max_by_key(|(path, _)| path.len())
+            .map_or((0, self.master_xpriv), |(path, xpriv)| (path.len(), *xpriv));
+        for (i, child) in children.iter().enumerate().skip(depth) {
+            xpriv = xpriv.derive_priv(self.secp, &[*child]).unwrap();
+            derived.push((DerivationPath::from(&children[..=i]), xpriv));
+        }
+
+        Xpub::from_priv(self.secp, &xpriv)
+    }
+}
+
+/// What a [`Matcher`] matched a candidate wallet on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Match {
+    /// The target address that was matched, if matching against addresses.
+    pub address: Option<Address>,
+    /// The full derivation path of the matched key, or the master path if the master key matched.
+    pub derivation_path: DerivationPath,
+}
+
+/// A test of whether a candidate wallet is the wallet being cracked.
+///
+/// Matchers run for every candidate passphrase on every worker thread, so they should bail out as
+/// early as possible. Closures taking a [`CandidateWallet`] are matchers too, for custom
+/// predicates, and matchers can be combined with [`All`] and [`Any`].
+pub trait Matcher: Send + Sync {
+    /// Test a candidate wallet, returning what it matched on, if anything.
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match>;
+}
+
+impl<F> Matcher for F
+where
+    F: Fn(&CandidateWallet) -> Option<Match> + Send + Sync,
+{
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match> {
+        self(wallet)
+    }
+}
+
+/// Matches if all of its matchers match, in order, and returns the first match with an address or
+/// a derivation path, so predicates returning a bare [`Match::default`] don't hide them. Put the
+/// cheapest and most selective matchers first, so mismatches bail out early. An empty `All`
+/// matches every wallet.
+pub struct All(pub Vec<Box<dyn Matcher>>);
+
+impl Matcher for All {
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match> {
+        let mut found = Match::default();
+        for matcher in &self.0 {
+            let matched = matcher.matches(wallet)?;
+            if found == Match::default() {
+                found = matched;
+            }
+        }
+
+        Some(found)
+    }
+}
+
+/// Matches if any of its matchers match, and returns the match of the first one that does.
+pub struct Any(pub Vec<Box<dyn Matcher>>);
+
+impl Matcher for Any {
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match> {
+        self.0.iter().find_map(|matcher| matcher.matches(wallet))
+    }
+}
+
+/// Matches the master key fingerprint. 32-bit fingerprints can collide, so fingerprint matches
+/// should be confirmed, e.g. by combining them with an [`AddressMatcher`] in [`All`].
+pub struct FingerprintMatcher(pub Fingerprint);
+
+impl Matcher for FingerprintMatcher {
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match> {
+        (wallet.fingerprint() == self.0).then(Match::default)
+    }
+}
+
+/// Matches an account extended public key, at the account path of any of the descriptors.
+pub struct XpubMatcher {
+    /// The target account extended public key.
+    xpub: Xpub,
+    /// The account paths to derive.
+    account_paths: Vec<DerivationPath>,
+}
+
+impl XpubMatcher {
+    /// Match `xpub` at the account paths of `descriptors`.
+    pub fn new(xpub: Xpub, descriptors: &[Descriptor]) -> Self {
+        let mut account_paths: Vec<DerivationPath> = Vec::new();
+        for descriptor in descriptors {
+            if !account_paths.contains(&descriptor.account_path) {
+                account_paths.push(descriptor.account_path.clone());
+            }
+        }
+
+        Self {
+            xpub,
+            account_paths,
+        }
+    }
+}
+
+impl Matcher for XpubMatcher {
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match> {
+        self.account_paths.iter().find_map(|account_path| {
+            let account_xpub = wallet.account_xpub(account_path);
+            (account_xpub.public_key == self.xpub.public_key
+                && account_xpub.chain_code == self.xpub.chain_code)
+                .then(|| Match {
+                    address: None,
+                    derivation_path: account_path.clone(),
+                })
+        })
+    }
+}
+
+/// Matches any of the target addresses, within the search width of the keychains of the
+/// descriptors.
+pub struct AddressMatcher {
+    /// The script pubkey payloads of the target addresses.
+    pub(crate) payloads: HashSet<Payload>,
+    /// The descriptor templates to derive addresses from.
+    pub(crate) descriptors: Vec<Descriptor>,
+    /// How many addresses to derive on each keychain.
+    pub(crate) search_width: u32,
+    /// The network of the target addresses.
+    pub(crate) network: Network,
+}
+
+impl AddressMatcher {
+    /// Match any of `addresses`, derived from `descriptors` up to `search_width` addresses deep.
+    /// Addresses are derived at normal indices, so the search width can't exceed `2^31`.
+    pub fn new(
+        addresses: &[Address],
+        descriptors: Vec<Descriptor>,
+        search_width: usize,
+        network: Network,
+    ) -> Result<Self, HashsatError> {
+        let payloads = addresses
+            .iter()
+            .map(|address| {
+                Payload::from_script_pubkey(&address.script_pubkey()).ok_or_else(|| {
+                    HashsatError::UnsupportedAddressType(
+                        address
+                            .address_type()
+                            .map_or("unknown".to_string(), |t| t.to_string()),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let search_width = u32::try_from(search_width)
+            .ok()
+            .filter(|&width| width <= 1 << 31)
+            .ok_or(HashsatError::InvalidSearchWidth(search_width))?;
+
+        Ok(Self {
+            payloads,
+            descriptors,
+            search_width,
+            network,
+        })
+    }
+}
+
+impl Matcher for AddressMatcher {
+    fn matches(&self, wallet: &CandidateWallet) -> Option<Match> {
+        let secp = wallet.secp();
+        for descriptor in &self.descriptors {
+            // The account key is derived once, and everything below it publicly, so no private
+            // key is ever materialized for the addresses.
+            let account_xpub = wallet.account_xpub(&descriptor.account_path);
+
+            // Derive some addresses from each keychain and see if any of them is a match.
+            for &keychain in &descriptor.keychains {
+                let keychain_xpub = account_xpub
+                    .derive_pub(secp, &[ChildNumber::Normal { index: keychain }])
+                    .unwrap();
+                for idx in 0..self.search_width {
+                    let xpub = keychain_xpub
+                        .derive_pub(secp, &[ChildNumber::Normal { index: idx }])
+                        .unwrap();
+
+                    if self.payloads.contains(&descriptor.payload(secp, &xpub)) {
+                        return Some(Match {
+                            address: Some(descriptor.address(secp, &xpub, self.network)),
+                            derivation_path: descriptor.derivation_path(keychain, idx),
+                        });
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// A custom matcher of a wallet, shared between runs.
+#[derive(Clone)]
+pub(crate) struct CustomMatcher(pub(crate) Arc<dyn Matcher>);
+
+impl fmt::Debug for CustomMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CustomMatcher")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bip39::Mnemonic;
+
+    use super::*;
+    use crate::testing::TEST_MNEMONIC;
+
+    #[test]
+    fn combinators() {
+        let secp = Secp256k1::new();
+        let mnemonic = Mnemonic::from_str(TEST_MNEMONIC).unwrap();
+        let seed = mnemonic.to_seed_normalized("");
+        let wallet = CandidateWallet::new(&secp, "", &seed, Network::Bitcoin);
+
+        let fingerprint = || -> Box<dyn Matcher> {
+            Box::new(FingerprintMatcher(
+                Fingerprint::from_str("73c5da0a").unwrap(),
+            ))
+        };
+        let never = || -> Box<dyn Matcher> { Box::new(|_: &CandidateWallet| None) };
+        let account = || -> Box<dyn Matcher> {
+            Box::new(|_: &CandidateWallet| {
+                Some(Match {
+                    address: None,
+                    derivation_path: DerivationPath::from_str("m/84'/0'/0'").unwrap(),
+                })
+            })
+        };
+
+        assert_eq!(fingerprint().matches(&wallet), Some(Match::default()));
+        assert_eq!(never().matches(&wallet), None);
+
+        // All of them must match, and the first match with a derivation path is returned.
+        assert_eq!(
+            All(vec![fingerprint(), account()]).matches(&wallet),
+            account().matches(&wallet)
+        );
+        assert_eq!(
+            All(vec![account(), fingerprint()]).matches(&wallet),
+            account().matches(&wallet)
+        );
+        assert_eq!(All(vec![fingerprint(), never()]).matches(&wallet), None);
+        assert_eq!(All(vec![]).matches(&wallet), Some(Match::default()));
+
+        // Any of them may match, and the first match is returned.
+        assert_eq!(
+            Any(vec![never(), fingerprint(), account()]).matches(&wallet),
+            Some(Match::default())
+        );
+        assert_eq!(Any(vec![never(), never()]).matches(&wallet), None);
+    }
+
+    /// Accounts are derived from the keys their paths share, with the same keys as from the master.
+    #[test]
+    fn shared_account_derivation() {
+        let secp = Secp256k1::new();
+        let seed = Mnemonic::from_str(TEST_MNEMONIC)
+            .unwrap()
+            .to_seed_normalized("");
+        let wallet = CandidateWallet::new(&secp, "", &seed, Network::Bitcoin);
+
+        let paths = [
+            "m/84'/0'/0'",
+            "m/84'/0'/1'",
+            "m/84'/1'/0'",
+            "m/84'/0'/0'",
+            "m",
+        ];
+        for path in paths {
+            let path = DerivationPath::from_str(path).unwrap();
+            let master_xpriv = wallet.master_xpriv();
+            let expected = Xpub::from_priv(&secp, &master_xpriv.derive_priv(&secp, &path).unwrap());
+            assert_eq!(wallet.account_xpub(&path), expected);
+        }
+        // `m/84'`, `m/84'/0'`, `m/84'/0'/0'`, `m/84'/0'/1'`, `m/84'/1'` and `m/84'/1'/0'`, each
+        // derived once.
+        assert_eq!(wallet.derived.borrow().len(), 6);
+    }
+
+    /// Search widths reaching hardened indices are rejected, by the matcher and the builder.
+    #[test]
+    fn search_width_limit() {
+        let address = Address::from_str("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu")
+            .unwrap()
+            .assume_checked();
+        let matcher = |search_width| {
+            AddressMatcher::new(
+                std::slice::from_ref(&address),
+                Vec::new(),
+                search_width,
+                Network::Bitcoin,
+            )
+        };
+        assert_eq!(matcher(1 << 31).unwrap().search_width, 1 << 31);
+        for search_width in [(1 << 31) + 1, usize::MAX] {
+            assert!(matches!(
+                matcher(search_width),
+                Err(HashsatError::InvalidSearchWidth(width)) if width == search_width
+            ));
+        }
+
+        let mnemonic = Mnemonic::from_str(TEST_MNEMONIC).unwrap();
+        let builder = crate::Cracker::builder(mnemonic).target_address(address.into_unchecked());
+        assert!(builder.clone().search_width(1 << 31).build().is_ok());
+        assert!(matches!(
+            builder.search_width(usize::MAX).build(),
+            Err(HashsatError::InvalidSearchWidth(usize::MAX))
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use crate::{error::HashsatError, source::CandidateSource};
+
+/// Iterator adapter that only yields the candidates of part `k` out of `n`.
+///
+/// Candidates are dealt round-robin between parts: the candidate at index `i` of the underlying
+/// iterator belongs to part `i % n`. Every candidate belongs to exactly one part, so parts are
+/// disjoint and their union is the full candidate space, as long as every part walks the same
+/// underlying iterator (i.e. the same configuration and seed).
+pub(crate) struct Partition<I> {
+    /// The underlying candidates.
+    candidates: I,
+    /// The zero-based part to yield.
+    part: u128,
+    /// The number of parts.
+    parts: u128,
+    /// The index of the next underlying candidate, modulo `parts`.
+    index: u128,
+}
+
+impl<I: CandidateSource> Partition<I> {
+    /// Yield the candidates of the zero-based part `part` out of `parts`.
+    pub(crate) fn new(candidates: I, part: usize, parts: usize) -> Self {
+        Self {
+            candidates,
+            part: part as u128,
+            parts: parts as u128,
+            index: 0,
+        }
+    }
+}
+
+impl<I: CandidateSource> Iterator for Partition<I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let candidate = self.candidates.next()?;
+            let index = self.index;
+            self.index = (self.index + 1) % self.parts;
+            if index == self.part {
+                return Some(candidate);
+            }
+        }
+    }
+}
+
+impl<I: CandidateSource> CandidateSource for Partition<I> {
+    /// The candidates at indices `part`, `part + n`, `part + 2n` and so on.
+    fn keyspace(&self) -> Option<u128> {
+        self.candidates.keyspace().map(|candidates| {
+            candidates / self.parts + u128::from(candidates % self.parts > self.part)
+        })
+    }
+
+    /// The position of the underlying candidates, followed by the index modulo `parts`.
+    fn position(&self) -> Vec<u128> {
+        let mut position = self.candidates.position();
+        position.push(self.index);
+        position
+    }
+
+    fn seek(&mut self, position: &[u128]) -> bool {
+        match position.split_last() {
+            Some((&index, position)) if self.candidates.seek(position) => {
+                self.index = index % self.parts;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn take_error(&mut self) -> Option<HashsatError> {
+        self.candidates.take_error()
+    }
+
+    fn skipped(&self) -> u128 {
+        self.candidates.skipped()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::mask::Mask;
+
+    #[test]
+    fn parts_cover_the_candidate_space() {
+        let mask = Mask::new("?d?d?1", &[Some("abc".to_string()), None, None, None]).unwrap();
+        let full: HashSet<String> = mask.iter().collect();
+
+        for parts in 1..=7 {
+            let mut union = HashSet::new();
+            for part in 0..parts {
+                for candidate in Partition::new(mask.iter(), part, parts) {
+                    // Parts are disjoint.
+                    assert!(union.insert(candidate));
+                }
+            }
+            assert_eq!(union, full);
+        }
+    }
+
+    #[test]
+    fn parts_resume() {
+        let mask = Mask::new("?d?d", &Default::default()).unwrap();
+        let mut partition = Partition::new(mask.iter(), 1, 3);
+        let head: Vec<String> = partition.by_ref().take(5).collect();
+        let position = partition.position();
+        let tail: Vec<String> = partition.collect();
+
+        let mut restored = Partition::new(mask.iter(), 1, 3);
+        assert!(restored.seek(&position));
+        assert_eq!(restored.collect::<Vec<_>>(), tail);
+        assert_eq!(head.len() + tail.len(), 33);
+    }
+}
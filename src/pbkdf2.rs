@@ -0,0 +1,759 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use core::fmt;
+use std::borrow::Cow;
+
+use bip39::Mnemonic;
+use bitcoin::hashes::{Hash, sha512};
+use unicode_normalization::UnicodeNormalization;
+
+/// The PBKDF2 iteration count of BIP39 seeds.
+const ROUNDS: usize = 2048;
+/// The SHA-512 block size, in bytes.
+const BLOCK_SIZE: usize = 128;
+/// The prefix of the PBKDF2 salt of BIP39 seeds, followed by the passphrase.
+const SALT_PREFIX: &[u8] = b"mnemonic";
+
+/// The SHA-512 initial hash value.
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The SHA-512 round constants.
+const K: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+/// The implementation used to run the SHA-512 compression function on many passphrases at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// One passphrase at a time, on any CPU.
+    Scalar,
+    /// Four passphrases at a time, on the 64-bit lanes of 256-bit AVX2 registers.
+    Avx2,
+    /// Eight passphrases at a time, on the 64-bit lanes of 512-bit AVX-512 registers.
+    Avx512,
+}
+
+impl Backend {
+    /// All backends, from the slowest to the fastest.
+    pub const ALL: [Backend; 3] = [Backend::Scalar, Backend::Avx2, Backend::Avx512];
+
+    /// The fastest backend supported by this CPU.
+    pub fn detect() -> Self {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(Backend::is_supported)
+            .unwrap_or(Backend::Scalar)
+    }
+
+    /// Whether this CPU supports the backend.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// How many passphrases the backend processes at once.
+    pub fn lanes(&self) -> usize {
+        match self {
+            Backend::Scalar => 1,
+            Backend::Avx2 => 4,
+            Backend::Avx512 => 8,
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Scalar => write!(f, "scalar"),
+            Backend::Avx2 => write!(f, "avx2"),
+            Backend::Avx512 => write!(f, "avx512"),
+        }
+    }
+}
+
+/// Batched BIP39 seed derivation (PBKDF2-HMAC-SHA512) for a fixed mnemonic.
+///
+/// The mnemonic is the HMAC key, so the SHA-512 states after absorbing the inner and outer key
+/// pads are computed once, and each of the 2 × 2048 HMAC compressions of every seed starts from
+/// them. All rounds after the first only hash 64-byte blocks, which are run on many passphrases
+/// at once on the lanes of SIMD registers.
+#[derive(Clone, Debug)]
+pub(crate) struct SeedDeriver {
+    /// The SHA-512 state after absorbing the inner key pad.
+    inner: [u64; 8],
+    /// The SHA-512 state after absorbing the outer key pad.
+    outer: [u64; 8],
+    /// The implementation of the compression function.
+    backend: Backend,
+}
+
+impl SeedDeriver {
+    /// Derive seeds of `mnemonic` with the fastest backend supported by this CPU.
+    pub(crate) fn new(mnemonic: &Mnemonic) -> Self {
+        Self::with_backend(mnemonic, Backend::detect())
+    }
+
+    /// Derive seeds of `mnemonic` with `backend`, which must be supported by this CPU.
+    pub(crate) fn with_backend(mnemonic: &Mnemonic, backend: Backend) -> Self {
+        assert!(backend.is_supported(), "unsupported backend {backend}");
+
+        // Keys longer than the block size are hashed, as per HMAC.
+        let password = mnemonic.words().collect::<Vec<_>>().join(" ");
+        let mut key = [0; BLOCK_SIZE];
+        if password.len() > BLOCK_SIZE {
+            key[..64].copy_from_slice(&sha512::Hash::hash(password.as_bytes()).to_byte_array());
+        } else {
+            key[..password.len()].copy_from_slice(password.as_bytes());
+        }
+
+        let pad = |byte: u8| {
+            let mut state = IV;
+            let block: [u8; BLOCK_SIZE] = key.map(|k| k ^ byte);
+            // SAFETY: scalar lanes run on any CPU.
+            unsafe { compress(&mut state, &block_words(&block)) };
+            state
+        };
+
+        Self {
+            inner: pad(0x36),
+            outer: pad(0x5c),
+            backend,
+        }
+    }
+
+    /// How many passphrases are processed at once. Batches should be a multiple of it.
+    pub(crate) fn lanes(&self) -> usize {
+        self.backend.lanes()
+    }
+
+    /// Derive the seeds of a batch of passphrases. Like [`Mnemonic::to_seed`], the passphrases are
+    /// NFKD-normalized first, so candidates can be in any normalization form.
+    pub(crate) fn seeds<S: AsRef<str>>(&self, passphrases: &[S]) -> Vec<[u8; 64]> {
+        let lanes = self.lanes();
+        let mut seeds = Vec::with_capacity(passphrases.len());
+        for chunk in passphrases.chunks(lanes) {
+            // The first round hashes the salt, which is of arbitrary length, so it is done one
+            // passphrase at a time. Spare lanes are filled with copies of the last passphrase.
+            let mut first: Vec<[u64; 8]> = chunk
+                .iter()
+                .map(|passphrase| self.first_round(&normalize(passphrase.as_ref())))
+                .collect();
+            first.resize(lanes, first[first.len() - 1]);
+
+            let derived = match self.backend {
+                // SAFETY: scalar lanes run on any CPU.
+                Backend::Scalar => unsafe { rounds::<u64>(&self.inner, &self.outer, &first) },
+                // SAFETY: `with_backend` asserted at runtime that this CPU supports the backend,
+                // which is the only way to create a deriver.
+                #[cfg(target_arch = "x86_64")]
+                Backend::Avx2 => unsafe { x86::rounds_avx2(&self.inner, &self.outer, &first) },
+                // SAFETY: as above.
+                #[cfg(target_arch = "x86_64")]
+                Backend::Avx512 => unsafe { x86::rounds_avx512(&self.inner, &self.outer, &first) },
+                #[cfg(not(target_arch = "x86_64"))]
+                _ => unreachable!("unsupported backend"),
+            };
+
+            seeds.extend(derived.iter().take(chunk.len()).map(|words| {
+                let mut seed = [0; 64];
+                for (bytes, word) in seed.chunks_exact_mut(8).zip(words) {
+                    bytes.copy_from_slice(&word.to_be_bytes());
+                }
+                seed
+            }));
+        }
+
+        seeds
+    }
+
+    /// The first PBKDF2 round, `U1 = HMAC(mnemonic, "mnemonic" || passphrase || INT(1))`.
+    fn first_round(&self, passphrase: &str) -> [u64; 8] {
+        let mut salt = Vec::with_capacity(SALT_PREFIX.len() + passphrase.len() + 4);
+        salt.extend_from_slice(SALT_PREFIX);
+        salt.extend_from_slice(passphrase.as_bytes());
+        salt.extend_from_slice(&1u32.to_be_bytes());
+
+        let inner = finalize(self.inner, &salt);
+        let inner: Vec<u8> = inner.iter().flat_map(|word| word.to_be_bytes()).collect();
+        finalize(self.outer, &inner)
+    }
+}
+
+/// NFKD-normalize a passphrase, as BIP39 does before deriving its seed. ASCII is left as is.
+fn normalize(passphrase: &str) -> Cow<'_, str> {
+    match passphrase.is_ascii() {
+        true => Cow::Borrowed(passphrase),
+        false => Cow::Owned(passphrase.nfkd().collect()),
+    }
+}
+
+/// Finish a SHA-512 hash of a message whose first block was already absorbed into `state`.
+fn finalize(mut state: [u64; 8], message: &[u8]) -> [u64; 8] {
+    let bits = ((BLOCK_SIZE + message.len()) as u128) * 8;
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLOCK_SIZE != BLOCK_SIZE - 16 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&bits.to_be_bytes());
+
+    for block in padded.chunks_exact(BLOCK_SIZE) {
+        // SAFETY: scalar lanes run on any CPU.
+        unsafe { compress(&mut state, &block_words(block.try_into().unwrap())) };
+    }
+    state
+}
+
+/// Read a block as big-endian 64-bit words.
+fn block_words(block: &[u8; BLOCK_SIZE]) -> [u64; 16] {
+    let mut words = [0; 16];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(8)) {
+        *word = u64::from_be_bytes(bytes.try_into().unwrap());
+    }
+    words
+}
+
+/// The 64-bit words of many independent SHA-512 computations, one per lane.
+///
+/// The operations are `unsafe`, as SIMD implementations are compiled for CPU features that aren't
+/// available on every CPU: they must only be called on CPUs that support the features of the
+/// implementation.
+trait Lanes: Copy {
+    /// How many computations run at once.
+    const LANES: usize;
+
+    /// The same word on every lane.
+    unsafe fn splat(word: u64) -> Self;
+    /// One word per lane.
+    unsafe fn load(words: &[u64]) -> Self;
+    /// Write one word per lane.
+    unsafe fn store(self, words: &mut [u64]);
+
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn xor(self, other: Self) -> Self;
+    /// `(e & f) ^ (!e & g)`.
+    unsafe fn ch(self, f: Self, g: Self) -> Self;
+    /// `(a & b) ^ (a & c) ^ (b & c)`.
+    unsafe fn maj(self, b: Self, c: Self) -> Self;
+    /// `rotr(x, 28) ^ rotr(x, 34) ^ rotr(x, 39)`.
+    unsafe fn big_sigma0(self) -> Self;
+    /// `rotr(x, 14) ^ rotr(x, 18) ^ rotr(x, 41)`.
+    unsafe fn big_sigma1(self) -> Self;
+    /// `rotr(x, 1) ^ rotr(x, 8) ^ (x >> 7)`.
+    unsafe fn small_sigma0(self) -> Self;
+    /// `rotr(x, 19) ^ rotr(x, 61) ^ (x >> 6)`.
+    unsafe fn small_sigma1(self) -> Self;
+}
+
+impl Lanes for u64 {
+    const LANES: usize = 1;
+
+    unsafe fn splat(word: u64) -> Self {
+        word
+    }
+    unsafe fn load(words: &[u64]) -> Self {
+        words[0]
+    }
+    unsafe fn store(self, words: &mut [u64]) {
+        words[0] = self;
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        self.wrapping_add(other)
+    }
+    #[inline(always)]
+    unsafe fn xor(self, other: Self) -> Self {
+        self ^ other
+    }
+    #[inline(always)]
+    unsafe fn ch(self, f: Self, g: Self) -> Self {
+        (self & f) ^ (!self & g)
+    }
+    #[inline(always)]
+    unsafe fn maj(self, b: Self, c: Self) -> Self {
+        (self & b) ^ (self & c) ^ (b & c)
+    }
+    #[inline(always)]
+    unsafe fn big_sigma0(self) -> Self {
+        self.rotate_right(28) ^ self.rotate_right(34) ^ self.rotate_right(39)
+    }
+    #[inline(always)]
+    unsafe fn big_sigma1(self) -> Self {
+        self.rotate_right(14) ^ self.rotate_right(18) ^ self.rotate_right(41)
+    }
+    #[inline(always)]
+    unsafe fn small_sigma0(self) -> Self {
+        self.rotate_right(1) ^ self.rotate_right(8) ^ (self >> 7)
+    }
+    #[inline(always)]
+    unsafe fn small_sigma1(self) -> Self {
+        self.rotate_right(19) ^ self.rotate_right(61) ^ (self >> 6)
+    }
+}
+
+/// The SHA-512 compression function, on every lane.
+///
+/// # Safety
+///
+/// This CPU must support the features of `L`.
+#[inline(always)]
+unsafe fn compress<L: Lanes>(state: &mut [L; 8], block: &[L; 16]) {
+    // SAFETY: the caller guarantees this CPU supports the features of `L`.
+    unsafe {
+        let mut w = *block;
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+        for (t, k) in K.iter().enumerate() {
+            // Expand the message schedule in place, over a ring of 16 words.
+            if t >= 16 {
+                w[t % 16] = w[t % 16]
+                    .add(w[(t - 15) % 16].small_sigma0())
+                    .add(w[(t - 7) % 16])
+                    .add(w[(t - 2) % 16].small_sigma1());
+            }
+
+            let t1 = h
+                .add(e.big_sigma1())
+                .add(e.ch(f, g))
+                .add(L::splat(*k))
+                .add(w[t % 16]);
+            let t2 = a.big_sigma0().add(a.maj(b, c));
+            h = g;
+            g = f;
+            f = e;
+            e = d.add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.add(t2);
+        }
+
+        for (word, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.add(v);
+        }
+    }
+}
+
+/// All PBKDF2 rounds after the first, `U2..U2048`, on every lane. Returns the XOR of all rounds.
+///
+/// # Safety
+///
+/// This CPU must support the features of `L`.
+#[inline(always)]
+unsafe fn rounds<L: Lanes>(
+    inner: &[u64; 8],
+    outer: &[u64; 8],
+    first: &[[u64; 8]],
+) -> Vec<[u64; 8]> {
+    debug_assert_eq!(first.len(), L::LANES);
+
+    // SAFETY: the caller guarantees this CPU supports the features of `L`.
+    unsafe {
+        // Transpose the first round into lanes.
+        let mut u = [L::splat(0); 8];
+        for (i, word) in u.iter_mut().enumerate() {
+            let words: Vec<u64> = first.iter().map(|first| first[i]).collect();
+            *word = L::load(&words);
+        }
+        let mut t = u;
+
+        // Messages are always one 64-byte hash after a key pad block, so their padding is fixed.
+        let mut block = [L::splat(0); 16];
+        block[8] = L::splat(0x8000_0000_0000_0000);
+        block[15] = L::splat(((BLOCK_SIZE + 64) * 8) as u64);
+        let inner = inner.map(|word| L::splat(word));
+        let outer = outer.map(|word| L::splat(word));
+
+        for _ in 1..ROUNDS {
+            block[..8].copy_from_slice(&u);
+            let mut state = inner;
+            compress(&mut state, &block);
+
+            block[..8].copy_from_slice(&state);
+            u = outer;
+            compress(&mut u, &block);
+
+            for (t, u) in t.iter_mut().zip(&u) {
+                *t = t.xor(*u);
+            }
+        }
+
+        // Transpose the result out of lanes.
+        let mut derived = vec![[0; 8]; L::LANES];
+        let mut words = vec![0; L::LANES];
+        for (i, word) in t.iter().enumerate() {
+            word.store(&mut words);
+            for (derived, word) in derived.iter_mut().zip(&words) {
+                derived[i] = *word;
+            }
+        }
+        derived
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::{Lanes, rounds};
+
+    /// `rotr(x, N)` on four lanes. AVX2 has no 64-bit rotation.
+    macro_rules! rotr256 {
+        ($x:expr, $n:literal) => {
+            _mm256_or_si256(
+                _mm256_srli_epi64::<$n>($x),
+                _mm256_slli_epi64::<{ 64 - $n }>($x),
+            )
+        };
+    }
+
+    /// Four lanes of an AVX2 register.
+    ///
+    /// Its operations are compiled for AVX2, so they must only be called on CPUs that support it.
+    #[derive(Clone, Copy)]
+    struct Avx2(__m256i);
+
+    impl Lanes for Avx2 {
+        const LANES: usize = 4;
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn splat(word: u64) -> Self {
+            Avx2(_mm256_set1_epi64x(word as i64))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn load(words: &[u64]) -> Self {
+            assert_eq!(words.len(), Self::LANES);
+            // SAFETY: `words` has four words.
+            Avx2(unsafe { _mm256_loadu_si256(words.as_ptr().cast()) })
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn store(self, words: &mut [u64]) {
+            assert_eq!(words.len(), Self::LANES);
+            // SAFETY: `words` has four words.
+            unsafe { _mm256_storeu_si256(words.as_mut_ptr().cast(), self.0) }
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn add(self, other: Self) -> Self {
+            Avx2(_mm256_add_epi64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn xor(self, other: Self) -> Self {
+            Avx2(_mm256_xor_si256(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn ch(self, f: Self, g: Self) -> Self {
+            Avx2(_mm256_xor_si256(
+                _mm256_and_si256(self.0, f.0),
+                _mm256_andnot_si256(self.0, g.0),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn maj(self, b: Self, c: Self) -> Self {
+            Avx2(_mm256_or_si256(
+                _mm256_and_si256(self.0, b.0),
+                _mm256_and_si256(c.0, _mm256_or_si256(self.0, b.0)),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn big_sigma0(self) -> Self {
+            Avx2(_mm256_xor_si256(
+                _mm256_xor_si256(rotr256!(self.0, 28), rotr256!(self.0, 34)),
+                rotr256!(self.0, 39),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn big_sigma1(self) -> Self {
+            Avx2(_mm256_xor_si256(
+                _mm256_xor_si256(rotr256!(self.0, 14), rotr256!(self.0, 18)),
+                rotr256!(self.0, 41),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn small_sigma0(self) -> Self {
+            Avx2(_mm256_xor_si256(
+                _mm256_xor_si256(rotr256!(self.0, 1), rotr256!(self.0, 8)),
+                _mm256_srli_epi64::<7>(self.0),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx2")]
+        unsafe fn small_sigma1(self) -> Self {
+            Avx2(_mm256_xor_si256(
+                _mm256_xor_si256(rotr256!(self.0, 19), rotr256!(self.0, 61)),
+                _mm256_srli_epi64::<6>(self.0),
+            ))
+        }
+    }
+
+    /// Eight lanes of an AVX-512 register.
+    ///
+    /// Its operations are compiled for AVX-512, so they must only be called on CPUs that support
+    /// it.
+    #[derive(Clone, Copy)]
+    struct Avx512(__m512i);
+
+    impl Lanes for Avx512 {
+        const LANES: usize = 8;
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn splat(word: u64) -> Self {
+            Avx512(_mm512_set1_epi64(word as i64))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn load(words: &[u64]) -> Self {
+            assert_eq!(words.len(), Self::LANES);
+            // SAFETY: `words` has eight words.
+            Avx512(unsafe { _mm512_loadu_si512(words.as_ptr().cast()) })
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn store(self, words: &mut [u64]) {
+            assert_eq!(words.len(), Self::LANES);
+            // SAFETY: `words` has eight words.
+            unsafe { _mm512_storeu_si512(words.as_mut_ptr().cast(), self.0) }
+        }
+
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn add(self, other: Self) -> Self {
+            Avx512(_mm512_add_epi64(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn xor(self, other: Self) -> Self {
+            Avx512(_mm512_xor_si512(self.0, other.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn ch(self, f: Self, g: Self) -> Self {
+            Avx512(_mm512_ternarylogic_epi64::<0xca>(self.0, f.0, g.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn maj(self, b: Self, c: Self) -> Self {
+            Avx512(_mm512_ternarylogic_epi64::<0xe8>(self.0, b.0, c.0))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn big_sigma0(self) -> Self {
+            Avx512(_mm512_ternarylogic_epi64::<0x96>(
+                _mm512_ror_epi64::<28>(self.0),
+                _mm512_ror_epi64::<34>(self.0),
+                _mm512_ror_epi64::<39>(self.0),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn big_sigma1(self) -> Self {
+            Avx512(_mm512_ternarylogic_epi64::<0x96>(
+                _mm512_ror_epi64::<14>(self.0),
+                _mm512_ror_epi64::<18>(self.0),
+                _mm512_ror_epi64::<41>(self.0),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn small_sigma0(self) -> Self {
+            Avx512(_mm512_ternarylogic_epi64::<0x96>(
+                _mm512_ror_epi64::<1>(self.0),
+                _mm512_ror_epi64::<8>(self.0),
+                _mm512_srli_epi64::<7>(self.0),
+            ))
+        }
+        #[inline]
+        #[target_feature(enable = "avx512f")]
+        unsafe fn small_sigma1(self) -> Self {
+            Avx512(_mm512_ternarylogic_epi64::<0x96>(
+                _mm512_ror_epi64::<19>(self.0),
+                _mm512_ror_epi64::<61>(self.0),
+                _mm512_srli_epi64::<6>(self.0),
+            ))
+        }
+    }
+
+    /// [`rounds`] on four lanes, compiled for AVX2.
+    ///
+    /// # Safety
+    ///
+    /// This CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn rounds_avx2(
+        inner: &[u64; 8],
+        outer: &[u64; 8],
+        first: &[[u64; 8]],
+    ) -> Vec<[u64; 8]> {
+        // SAFETY: the caller guarantees this CPU supports AVX2.
+        unsafe { rounds::<Avx2>(inner, outer, first) }
+    }
+
+    /// [`rounds`] on eight lanes, compiled for AVX-512.
+    ///
+    /// # Safety
+    ///
+    /// This CPU must support AVX-512.
+    #[target_feature(enable = "avx512f")]
+    pub(super) unsafe fn rounds_avx512(
+        inner: &[u64; 8],
+        outer: &[u64; 8],
+        first: &[[u64; 8]],
+    ) -> Vec<[u64; 8]> {
+        // SAFETY: the caller guarantees this CPU supports AVX-512.
+        unsafe { rounds::<Avx512>(inner, outer, first) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::testing::TEST_MNEMONIC;
+
+    #[test]
+    fn seeds_match_bip39() {
+        let mnemonics = [
+            // Short enough for the key to be used as is.
+            TEST_MNEMONIC,
+            // Long enough for the key to be hashed.
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+        ];
+        let long = "x".repeat(300);
+        let passphrases = [
+            "",
+            "TREZOR",
+            "a",
+            "hunter2",
+            "pässwörd",
+            // Decomposed, with combining diaeresis.
+            "pa\u{308}sswo\u{308}rd",
+            "€😀 spaces and symbols !@#",
+            // Long enough for the salt to span many blocks.
+            &long,
+            "last",
+            "one more than a multiple of every lane count",
+        ];
+
+        for mnemonic in mnemonics {
+            let mnemonic = Mnemonic::from_str(mnemonic).unwrap();
+            let expected: Vec<[u8; 64]> = passphrases
+                .iter()
+                .map(|passphrase| mnemonic.to_seed(*passphrase))
+                .collect();
+
+            for backend in Backend::ALL.into_iter().filter(Backend::is_supported) {
+                let deriver = SeedDeriver::with_backend(&mnemonic, backend);
+                assert_eq!(deriver.seeds(&passphrases), expected, "{backend}");
+            }
+        }
+    }
+}
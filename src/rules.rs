@@ -0,0 +1,454 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{fs, path::Path, str::FromStr, sync::Arc};
+
+use crate::{error::HashsatError, source::CandidateSource};
+
+/// The maximum length of a mangled word, in bytes, as in hashcat.
+const MAX_WORD_BYTES: usize = 256;
+
+/// A single mangling function, in hashcat/John the Ripper rule syntax.
+///
+/// Positions (`N`, `M`) are encoded as a single character: `0-9` for 0 to 9 and `A-Z` for 10 to
+/// 35. Functions that reference positions outside of the word leave it unchanged, and so do
+/// functions that would grow it past [`MAX_WORD_BYTES`], so rules like `dddddddddd` can't exhaust
+/// memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Function {
+    /// `:` do nothing.
+    Noop,
+    /// `l` lowercase all characters.
+    Lowercase,
+    /// `u` uppercase all characters.
+    Uppercase,
+    /// `c` capitalize the first character and lowercase the rest.
+    Capitalize,
+    /// `C` lowercase the first character and uppercase the rest.
+    InvertCapitalize,
+    /// `t` toggle the case of all characters.
+    ToggleCase,
+    /// `TN` toggle the case of the character at position N.
+    ToggleAt(usize),
+    /// `r` reverse the word.
+    Reverse,
+    /// `d` duplicate the word.
+    Duplicate,
+    /// `pN` append the word to itself N times.
+    DuplicateN(usize),
+    /// `f` append the reversed word.
+    Reflect,
+    /// `{` rotate the word left.
+    RotateLeft,
+    /// `}` rotate the word right.
+    RotateRight,
+    /// `$X` append character X.
+    Append(char),
+    /// `^X` prepend character X.
+    Prepend(char),
+    /// `[` delete the first character.
+    DeleteFirst,
+    /// `]` delete the last character.
+    DeleteLast,
+    /// `DN` delete the character at position N.
+    DeleteAt(usize),
+    /// `xNM` extract M characters, starting at position N.
+    Extract(usize, usize),
+    /// `ONM` omit M characters, starting at position N.
+    Omit(usize, usize),
+    /// `iNX` insert character X at position N.
+    Insert(usize, char),
+    /// `oNX` overwrite the character at position N with X.
+    Overwrite(usize, char),
+    /// `'N` truncate the word at position N.
+    Truncate(usize),
+    /// `sXY` replace all instances of X with Y.
+    Substitute(char, char),
+    /// `@X` purge all instances of X.
+    Purge(char),
+    /// `zN` duplicate the first character N times.
+    DuplicateFirst(usize),
+    /// `ZN` duplicate the last character N times.
+    DuplicateLast(usize),
+    /// `q` duplicate every character.
+    DuplicateAll,
+}
+
+/// A mangling rule: a sequence of [`Function`]s applied left to right on a base word.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Rule(Vec<Function>);
+
+impl Rule {
+    /// Apply this rule to `word`, returning the mangled variant.
+    pub(crate) fn apply(&self, word: &str) -> String {
+        let mut w: Vec<char> = word.chars().collect();
+        for function in &self.0 {
+            let before = w.clone();
+            match *function {
+                Function::Noop => {}
+                Function::Lowercase => w = w.iter().flat_map(|c| c.to_lowercase()).collect(),
+                Function::Uppercase => w = w.iter().flat_map(|c| c.to_uppercase()).collect(),
+                Function::Capitalize => {
+                    w = w.iter().flat_map(|c| c.to_lowercase()).collect();
+                    if let Some(&first) = w.first() {
+                        w.splice(0..1, first.to_uppercase());
+                    }
+                }
+                Function::InvertCapitalize => {
+                    w = w.iter().flat_map(|c| c.to_uppercase()).collect();
+                    if let Some(&first) = w.first() {
+                        w.splice(0..1, first.to_lowercase());
+                    }
+                }
+                Function::ToggleCase => w = w.iter().flat_map(|c| toggle(*c)).collect(),
+                Function::ToggleAt(n) => {
+                    if n < w.len() {
+                        w.splice(n..=n, toggle(w[n]));
+                    }
+                }
+                Function::Reverse => w.reverse(),
+                Function::Duplicate => w.extend_from_within(..),
+                Function::DuplicateN(n) => {
+                    let len = w.len();
+                    for _ in 0..n {
+                        w.extend_from_within(..len);
+                    }
+                }
+                Function::Reflect => {
+                    let reversed: Vec<char> = w.iter().rev().copied().collect();
+                    w.extend(reversed);
+                }
+                Function::RotateLeft => {
+                    if !w.is_empty() {
+                        w.rotate_left(1);
+                    }
+                }
+                Function::RotateRight => {
+                    if !w.is_empty() {
+                        w.rotate_right(1);
+                    }
+                }
+                Function::Append(c) => w.push(c),
+                Function::Prepend(c) => w.insert(0, c),
+                Function::DeleteFirst => {
+                    if !w.is_empty() {
+                        w.remove(0);
+                    }
+                }
+                Function::DeleteLast => {
+                    w.pop();
+                }
+                Function::DeleteAt(n) => {
+                    if n < w.len() {
+                        w.remove(n);
+                    }
+                }
+                Function::Extract(n, m) => {
+                    if n + m <= w.len() {
+                        w = w[n..n + m].to_vec();
+                    }
+                }
+                Function::Omit(n, m) => {
+                    if n + m <= w.len() {
+                        w.drain(n..n + m);
+                    }
+                }
+                Function::Insert(n, c) => {
+                    if n <= w.len() {
+                        w.insert(n, c);
+                    }
+                }
+                Function::Overwrite(n, c) => {
+                    if n < w.len() {
+                        w[n] = c;
+                    }
+                }
+                Function::Truncate(n) => w.truncate(n),
+                Function::Substitute(x, y) => {
+                    w.iter_mut().filter(|c| **c == x).for_each(|c| *c = y)
+                }
+                Function::Purge(x) => w.retain(|c| *c != x),
+                Function::DuplicateFirst(n) => {
+                    if let Some(&first) = w.first() {
+                        w.splice(0..0, std::iter::repeat_n(first, n));
+                    }
+                }
+                Function::DuplicateLast(n) => {
+                    if let Some(&last) = w.last() {
+                        w.extend(std::iter::repeat_n(last, n));
+                    }
+                }
+                Function::DuplicateAll => w = w.iter().flat_map(|c| [*c, *c]).collect(),
+            }
+            // Undo functions that grew the word past the cap, counting multi-byte characters.
+            let len = byte_len(&w);
+            if len > MAX_WORD_BYTES && len > byte_len(&before) {
+                w = before;
+            }
+        }
+
+        w.into_iter().collect()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = HashsatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| HashsatError::InvalidRule(s.to_string(), reason);
+
+        let mut chars = s.chars();
+        // Read the next character as the argument of function `f`.
+        let arg = |chars: &mut std::str::Chars, f: char| {
+            chars
+                .next()
+                .ok_or_else(|| invalid(format!("missing argument for `{f}`")))
+        };
+        // Read the next character as a position argument of function `f`.
+        let pos = |chars: &mut std::str::Chars, f: char| match arg(chars, f)? {
+            c @ '0'..='9' => Ok(c as usize - '0' as usize),
+            c @ 'A'..='Z' => Ok(c as usize - 'A' as usize + 10),
+            c => Err(invalid(format!("invalid position `{c}` for `{f}`"))),
+        };
+
+        let mut functions = Vec::new();
+        while let Some(c) = chars.next() {
+            let function = match c {
+                ' ' | '\t' => continue,
+                ':' => Function::Noop,
+                'l' => Function::Lowercase,
+                'u' => Function::Uppercase,
+                'c' => Function::Capitalize,
+                'C' => Function::InvertCapitalize,
+                't' => Function::ToggleCase,
+                'T' => Function::ToggleAt(pos(&mut chars, c)?),
+                'r' => Function::Reverse,
+                'd' => Function::Duplicate,
+                'p' => Function::DuplicateN(pos(&mut chars, c)?),
+                'f' => Function::Reflect,
+                '{' => Function::RotateLeft,
+                '}' => Function::RotateRight,
+                '$' => Function::Append(arg(&mut chars, c)?),
+                '^' => Function::Prepend(arg(&mut chars, c)?),
+                '[' => Function::DeleteFirst,
+                ']' => Function::DeleteLast,
+                'D' => Function::DeleteAt(pos(&mut chars, c)?),
+                'x' => Function::Extract(pos(&mut chars, c)?, pos(&mut chars, c)?),
+                'O' => Function::Omit(pos(&mut chars, c)?, pos(&mut chars, c)?),
+                'i' => Function::Insert(pos(&mut chars, c)?, arg(&mut chars, c)?),
+                'o' => Function::Overwrite(pos(&mut chars, c)?, arg(&mut chars, c)?),
+                '\'' => Function::Truncate(pos(&mut chars, c)?),
+                's' => Function::Substitute(arg(&mut chars, c)?, arg(&mut chars, c)?),
+                '@' => Function::Purge(arg(&mut chars, c)?),
+                'z' => Function::DuplicateFirst(pos(&mut chars, c)?),
+                'Z' => Function::DuplicateLast(pos(&mut chars, c)?),
+                'q' => Function::DuplicateAll,
+                _ => return Err(invalid(format!("unknown function `{c}`"))),
+            };
+            functions.push(function);
+        }
+
+        Ok(Rule(functions))
+    }
+}
+
+/// Load the rules from a rule file, one rule per line.
+///
+/// Empty lines and lines starting with `#` are skipped. A file without any rules is an error, as it
+/// would yield no candidates at all.
+pub(crate) fn load_rules(path: &Path) -> Result<Vec<Rule>, HashsatError> {
+    let rules = fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(Rule::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match rules.is_empty() {
+        true => Err(HashsatError::NoRules(path.display().to_string())),
+        false => Ok(rules),
+    }
+}
+
+/// Iterator adapter that expands every base word of `words` into one variant per rule.
+pub(crate) struct Mangle<I> {
+    /// The base words.
+    words: I,
+    /// The rules to apply on each base word.
+    rules: Arc<[Rule]>,
+    /// The base word currently being expanded.
+    word: Option<String>,
+    /// The position of the base words before `word` was taken.
+    word_position: Vec<u128>,
+    /// The index of the next rule to apply on `word`.
+    rule_idx: usize,
+}
+
+impl<I: CandidateSource> Mangle<I> {
+    pub(crate) fn new(words: I, rules: Vec<Rule>) -> Self {
+        Self {
+            words,
+            rules: rules.into(),
+            word: None,
+            word_position: Vec::new(),
+            rule_idx: 0,
+        }
+    }
+}
+
+impl<I: CandidateSource> Iterator for Mangle<I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rules.is_empty() {
+            return None;
+        }
+
+        loop {
+            if let Some(word) = &self.word
+                && let Some(rule) = self.rules.get(self.rule_idx)
+            {
+                self.rule_idx += 1;
+                return Some(rule.apply(word));
+            }
+            self.word_position = self.words.position();
+            self.word = Some(self.words.next()?);
+            self.rule_idx = 0;
+        }
+    }
+}
+
+impl<I: CandidateSource> CandidateSource for Mangle<I> {
+    fn keyspace(&self) -> Option<u128> {
+        self.words
+            .keyspace()
+            .and_then(|words| words.checked_mul(self.rules.len() as u128))
+    }
+
+    /// The position of the base words, followed by the index of the next rule to apply.
+    fn position(&self) -> Vec<u128> {
+        let mut position = match self.word {
+            Some(_) => self.word_position.clone(),
+            None => self.words.position(),
+        };
+        position.push(self.rule_idx as u128);
+        position
+    }
+
+    fn seek(&mut self, position: &[u128]) -> bool {
+        let Some((&rule_idx, word_position)) = position.split_last() else {
+            return false;
+        };
+        if !self.words.seek(word_position) {
+            return false;
+        }
+        self.word = None;
+        self.rule_idx = 0;
+        if rule_idx > 0 {
+            self.word_position = word_position.to_vec();
+            self.word = self.words.next();
+            self.rule_idx = rule_idx as usize;
+        }
+        true
+    }
+
+    fn take_error(&mut self) -> Option<HashsatError> {
+        self.words.take_error()
+    }
+
+    fn skipped(&self) -> u128 {
+        self.words.skipped()
+    }
+}
+
+/// The length of `w` in bytes, as UTF-8.
+fn byte_len(w: &[char]) -> usize {
+    w.iter().map(|c| c.len_utf8()).sum()
+}
+
+/// Toggle the case of a character.
+fn toggle(c: char) -> Vec<char> {
+    if c.is_uppercase() {
+        c.to_lowercase().collect()
+    } else {
+        c.to_uppercase().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mask::Mask, testing::TempFile};
+
+    fn apply(rule: &str, word: &str) -> String {
+        Rule::from_str(rule).unwrap().apply(word)
+    }
+
+    #[test]
+    fn hashcat_rule_functions() {
+        assert_eq!(apply(":", "rex"), "rex");
+        assert_eq!(apply("c $1 $2 $3", "rex"), "Rex123");
+        assert_eq!(apply("^! u", "rex"), "!REX");
+        assert_eq!(apply("sa4 se3 so0", "password"), "p4ssw0rd");
+        assert_eq!(apply("t", "rEx"), "ReX");
+        assert_eq!(apply("T0 T9", "rex"), "Rex");
+        assert_eq!(apply("d", "rex"), "rexrex");
+        assert_eq!(apply("p2", "ab"), "ababab");
+        assert_eq!(apply("r", "rex"), "xer");
+        assert_eq!(apply("f", "ab"), "abba");
+        assert_eq!(apply("'3", "passphrase"), "pas");
+        assert_eq!(apply("[ ]", "rexy"), "ex");
+        assert_eq!(apply("i1- o0R", "rex"), "R-ex");
+        assert_eq!(apply("x14", "passphrase"), "assp");
+        assert_eq!(apply("@s", "passphrase"), "paphrae");
+        assert_eq!(apply("z2 Z1", "ab"), "aaabb");
+
+        assert!(Rule::from_str("$").is_err());
+        assert!(Rule::from_str("T!").is_err());
+        assert!(Rule::from_str("?").is_err());
+    }
+
+    #[test]
+    fn word_length_cap() {
+        // Doubling stops before the word would grow past 256 bytes, at 3 * 2^6 bytes.
+        assert_eq!(apply(&"d".repeat(30), "rex"), "rex".repeat(64));
+        assert_eq!(apply("p9 p9 p9", "rex"), "rex".repeat(10));
+        assert_eq!(apply("q q q q q q q q q q", "ab").len(), 256);
+        assert_eq!(apply("zZ zZ zZ zZ zZ zZ zZ zZ zZ", "a").len(), 246);
+        // Multi-byte characters count for their UTF-8 length.
+        assert_eq!(apply(&"d".repeat(30), "\u{e9}").chars().count(), 128);
+
+        // Words longer than the cap are left as they are, but not grown any further.
+        let long = "x".repeat(300);
+        assert_eq!(apply("d $1 u", &long), "X".repeat(300));
+
+        // Substitutions and overwrites can grow a word too, when they swap in wider characters.
+        let capped = "a".repeat(256);
+        assert_eq!(apply("sa\u{20ac}", &capped), capped);
+        assert_eq!(apply("o0\u{20ac}", &capped), capped);
+        assert_eq!(apply("sa\u{20ac}", "aa"), "\u{20ac}\u{20ac}");
+    }
+
+    #[test]
+    fn mangle_expands_every_word() {
+        let rules = vec![Rule::from_str(":").unwrap(), Rule::from_str("$1").unwrap()];
+        let words = Mask::new("?1", &[Some("ab".to_string()), None, None, None])
+            .unwrap()
+            .iter();
+        let variants: Vec<String> = Mangle::new(words, rules).collect();
+        assert_eq!(variants, ["a", "a1", "b", "b1"]);
+    }
+
+    #[test]
+    fn rule_files() {
+        let rules = TempFile::new("rules", "# leetspeak\n\nsa4 se3\n$1\n");
+        assert_eq!(load_rules(rules.path()).unwrap().len(), 2);
+
+        // Files without rules would silently yield no candidates.
+        for contents in ["", "\n  \n", "# just a comment\n"] {
+            fs::write(rules.path(), contents).unwrap();
+            assert!(matches!(
+                load_rules(rules.path()),
+                Err(HashsatError::NoRules(_))
+            ));
+        }
+    }
+}
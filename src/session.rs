@@ -0,0 +1,328 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use bitcoin::{
+    bip32::Xpriv,
+    hashes::{Hash, HashEngine, sha256},
+    key::Secp256k1,
+};
+
+use crate::{error::HashsatError, types::Wallet};
+
+/// The version of the session file format.
+const SESSION_VERSION: u32 = 2;
+
+/// How many candidates of a custom source are hashed into the run configuration, as the source
+/// itself can't be.
+const GENERATOR_SAMPLE: usize = 1024;
+
+/// A checkpoint of a cracking run, from which it can be restored without re-testing candidates.
+///
+/// The run configuration is only stored as a hash, which is checked against the configuration on
+/// restore. The mnemonic is left out of it, as it is secret and the hash is unsalted, and only the
+/// fingerprint of its master key without a passphrase is hashed, to tell wallets apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Session {
+    /// The hash of the run configuration.
+    pub(crate) config: sha256::Hash,
+    /// The seed used to scramble the alphabet.
+    pub(crate) seed: u64,
+    /// The position of the candidate passphrase iterator.
+    pub(crate) position: Vec<u128>,
+    /// How many candidates were tested.
+    pub(crate) tries: usize,
+}
+
+impl Session {
+    /// Hash the run configuration of a [`Wallet`]: the fingerprint of its mnemonic, its targets
+    /// and search settings, and what its candidates are generated from, i.e. the characters of the
+    /// alphabet and the mask, and the contents of the wordlist and rule files. Custom sources are
+    /// sampled instead, and stdin can't be hashed.
+    pub(crate) fn config_hash(wallet: &Wallet) -> Result<sha256::Hash, HashsatError> {
+        let mut engine = sha256::Hash::engine();
+        let mut field = |name: &str, value: &[u8]| {
+            engine.input(name.as_bytes());
+            engine.input(&(value.len() as u64).to_be_bytes());
+            engine.input(value);
+        };
+
+        let seed = wallet.mnemonic.to_seed_normalized("");
+        let master_xpriv = Xpriv::new_master(wallet.network, &seed).unwrap();
+        field(
+            "mnemonic",
+            master_xpriv.fingerprint(&Secp256k1::new()).as_bytes(),
+        );
+        field("network", wallet.network.to_string().as_bytes());
+        for address in &wallet.target_addresses {
+            field("target address", address.to_string().as_bytes());
+        }
+        if let Some(xpub) = &wallet.target_xpub {
+            field("target xpub", xpub.to_string().as_bytes());
+        }
+        if let Some(fingerprint) = &wallet.target_fingerprint {
+            field("target fingerprint", fingerprint.as_bytes());
+        }
+        for descriptor in &wallet.descriptors {
+            field("descriptor", descriptor.to_string().as_bytes());
+        }
+        field("matcher", &[wallet.custom_matcher.is_some() as u8]);
+        field("search width", &(wallet.search_width as u64).to_be_bytes());
+        let (min, max) = wallet.passphrase_length_range;
+        field("min length", &(min as u64).to_be_bytes());
+        field("max length", &(max as u64).to_be_bytes());
+        if let Some((k, n)) = wallet.part {
+            field("part", format!("{k}/{n}").as_bytes());
+        }
+        let source = match (&wallet.generator, &wallet.wordlist, &wallet.mask) {
+            (Some(_), _, _) => "custom",
+            (_, Some(_), _) => "wordlist",
+            (_, _, Some(_)) => "mask",
+            _ => "alphabet",
+        };
+        field("source", source.as_bytes());
+        field(
+            "alphabet",
+            String::from_iter(wallet.alphabet.chars()).as_bytes(),
+        );
+        if let Some(mask) = &wallet.mask {
+            for charset in mask.charsets() {
+                field("charset", String::from_iter(charset).as_bytes());
+            }
+        }
+        if let Some(rules) = &wallet.rules {
+            field("rules", &fs::read(rules)?);
+        }
+        if let Some(wordlist) = &wallet.wordlist
+            && wordlist != Path::new("-")
+        {
+            field("wordlist", hash_file(wordlist)?.as_byte_array());
+        }
+        if let Some(generator) = &wallet.generator {
+            for candidate in generator.source().take(GENERATOR_SAMPLE) {
+                field("candidate", candidate.as_bytes());
+            }
+        }
+
+        Ok(sha256::Hash::from_engine(engine))
+    }
+
+    /// Load the session of a [`Wallet`] to restore, if any. It must have been created with the
+    /// same configuration.
+    pub(crate) fn restore(wallet: &Wallet) -> Result<Option<Self>, HashsatError> {
+        match &wallet.session {
+            Some(path) if wallet.restore => {
+                let session = Session::load(path)?;
+                if session.config != Session::config_hash(wallet)? {
+                    return Err(HashsatError::SessionMismatch(path.display().to_string()));
+                }
+                Ok(Some(session))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Load a session from `path`.
+    pub(crate) fn load(path: &Path) -> Result<Self, HashsatError> {
+        let invalid = |reason: &str| {
+            HashsatError::InvalidSession(path.display().to_string(), reason.to_string())
+        };
+
+        let contents = fs::read_to_string(path)?;
+        let mut fields = std::collections::HashMap::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("malformed line"))?;
+            fields.insert(key.trim(), value.trim());
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| invalid(&format!("missing `{key}`")))
+        };
+
+        if field("version")? != SESSION_VERSION.to_string() {
+            return Err(invalid("unsupported version"));
+        }
+
+        Ok(Self {
+            config: field("config")?
+                .parse()
+                .map_err(|_| invalid("invalid `config`"))?,
+            seed: field("seed")?
+                .parse()
+                .map_err(|_| invalid("invalid `seed`"))?,
+            position: field("position")?
+                .split(',')
+                .filter(|p| !p.is_empty())
+                .map(|p| p.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid("invalid `position`"))?,
+            tries: field("tries")?
+                .parse()
+                .map_err(|_| invalid("invalid `tries`"))?,
+        })
+    }
+
+    /// Atomically write the session to `path`.
+    pub(crate) fn save(&self, path: &Path) -> Result<(), HashsatError> {
+        let contents = format!(
+            "version={}\nconfig={}\nseed={}\nposition={}\ntries={}\n",
+            SESSION_VERSION,
+            self.config,
+            self.seed,
+            self.position
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            self.tries,
+        );
+
+        let mut tmp = PathBuf::from(path);
+        tmp.as_mut_os_string().push(".tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, path)?;
+
+        Ok(())
+    }
+}
+
+/// Hash the contents of a file, streaming it so files of any size can be hashed.
+fn hash_file(path: &Path) -> Result<sha256::Hash, HashsatError> {
+    let mut file = File::open(path)?;
+    let mut engine = sha256::Hash::engine();
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(sha256::Hash::from_engine(engine)),
+            n => engine.input(&buf[..n]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bip39::Mnemonic;
+    use bitcoin::bip32::Fingerprint;
+
+    use super::*;
+    use crate::{
+        alphabet::Alphabet,
+        builder::CrackerBuilder,
+        cracker::Cracker,
+        mask::Mask,
+        source::{CandidateSource, candidates},
+        testing::{self, TempFile, wallet},
+    };
+
+    /// A builder for the test wallet, targeting a fingerprint none of the candidates has.
+    fn builder() -> CrackerBuilder {
+        testing::builder().target_fingerprint(Fingerprint::default())
+    }
+
+    #[test]
+    fn save_and_load() {
+        let file = TempFile::new("session-round-trip", "");
+        let session = Session {
+            config: Session::config_hash(&wallet(builder())).unwrap(),
+            seed: u64::MAX,
+            position: vec![3, 0, u128::MAX],
+            tries: 42,
+        };
+        session.save(file.path()).unwrap();
+        assert_eq!(Session::load(file.path()).unwrap(), session);
+    }
+
+    /// The configuration hash covers what the candidates are generated from, not just its name.
+    #[test]
+    fn config_hash_covers_sources() {
+        let hash = |builder: CrackerBuilder| Session::config_hash(&wallet(builder)).unwrap();
+        let mask = |charset: &str| Mask::new("?1", &[Some(charset.to_string()), None, None, None]);
+
+        assert_ne!(
+            hash(builder().mask(mask("abc").unwrap())),
+            hash(builder().mask(mask("abd").unwrap()))
+        );
+
+        let rules = TempFile::new("session-rules", ":\n");
+        let before = hash(builder().rules(rules.path()));
+        fs::write(rules.path(), ":\nu\n").unwrap();
+        assert_ne!(before, hash(builder().rules(rules.path())));
+
+        let wordlist = TempFile::new("session-wordlist", "foo\n");
+        let before = hash(builder().wordlist(wordlist.path()));
+        fs::write(wordlist.path(), "bar\n").unwrap();
+        assert_ne!(before, hash(builder().wordlist(wordlist.path())));
+    }
+
+    /// The configuration hash tells wallets apart without hashing their mnemonic.
+    #[test]
+    fn config_hash_leaves_out_mnemonic() {
+        let legal = Mnemonic::from_str(
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        )
+        .unwrap();
+        let other = wallet(Cracker::builder(legal).target_fingerprint(Fingerprint::default()));
+        assert_ne!(
+            Session::config_hash(&wallet(builder())).unwrap(),
+            Session::config_hash(&other).unwrap()
+        );
+    }
+
+    /// A run restored from a checkpoint tests every remaining candidate exactly once.
+    #[test]
+    fn resume_without_skips_or_duplicates() {
+        let wordlist = TempFile::new("session-resume-wordlist", "foo\nbar\n\nbaz\nqux\n");
+        let rules = TempFile::new("session-resume-rules", ":\nu\nr\n");
+        let checkpoint = TempFile::new("session-resume-checkpoint", "");
+
+        let wallets = [
+            wallet(
+                builder()
+                    .alphabet(Alphabet::from_str("chars:ab1").unwrap())
+                    .passphrase_length_range(0, 3),
+            ),
+            wallet(
+                builder()
+                    .mask(Mask::new("?d?1", &[Some("xy".to_string()), None, None, None]).unwrap())
+                    .rules(rules.path()),
+            ),
+            wallet(
+                builder()
+                    .wordlist(wordlist.path())
+                    .rules(rules.path())
+                    .part(2, 3),
+            ),
+        ];
+        for wallet in wallets {
+            let full: Vec<String> = candidates(&wallet, 7).unwrap().collect();
+            for stop in 0..=full.len() {
+                let mut run = candidates(&wallet, 7).unwrap();
+                let mut tested: Vec<String> = run.by_ref().take(stop).collect();
+                Session {
+                    config: Session::config_hash(&wallet).unwrap(),
+                    seed: 7,
+                    position: run.position(),
+                    tries: stop,
+                }
+                .save(checkpoint.path())
+                .unwrap();
+
+                let session = Session::load(checkpoint.path()).unwrap();
+                let mut restored = candidates(&wallet, session.seed).unwrap();
+                assert!(restored.seek(&session.position));
+                tested.extend(restored);
+                assert_eq!(tested, full, "stopped at {stop}");
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use bitcoin::{NetworkKind, base58, bip32::Xpub};
+
+use crate::{descriptor::ScriptType, error::HashsatError};
+
+/// The BIP32 version bytes for mainnet extended public keys (`xpub`).
+const VERSION_XPUB: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
+/// The SLIP-132 version bytes for mainnet P2SH-P2WPKH extended public keys (`ypub`).
+const VERSION_YPUB: [u8; 4] = [0x04, 0x9D, 0x7C, 0xB2];
+/// The SLIP-132 version bytes for mainnet P2WPKH extended public keys (`zpub`).
+const VERSION_ZPUB: [u8; 4] = [0x04, 0xB2, 0x47, 0x46];
+/// The BIP32 version bytes for testnet extended public keys (`tpub`).
+const VERSION_TPUB: [u8; 4] = [0x04, 0x35, 0x87, 0xCF];
+/// The SLIP-132 version bytes for testnet P2SH-P2WPKH extended public keys (`upub`).
+const VERSION_UPUB: [u8; 4] = [0x04, 0x4A, 0x52, 0x62];
+/// The SLIP-132 version bytes for testnet P2WPKH extended public keys (`vpub`).
+const VERSION_VPUB: [u8; 4] = [0x04, 0x5F, 0x1C, 0xF6];
+
+/// The version bytes, with the network and script type they imply. Plain BIP32 version bytes are
+/// used by wallets of every script type, so they don't imply any.
+const VERSIONS: [([u8; 4], NetworkKind, Option<ScriptType>); 6] = [
+    (VERSION_XPUB, NetworkKind::Main, None),
+    (VERSION_YPUB, NetworkKind::Main, Some(ScriptType::ShWpkh)),
+    (VERSION_ZPUB, NetworkKind::Main, Some(ScriptType::Wpkh)),
+    (VERSION_TPUB, NetworkKind::Test, None),
+    (VERSION_UPUB, NetworkKind::Test, Some(ScriptType::ShWpkh)),
+    (VERSION_VPUB, NetworkKind::Test, Some(ScriptType::Wpkh)),
+];
+
+/// Decode an extended public key with SLIP-132 version bytes (`xpub`, `ypub`, `zpub` and their
+/// testnet counterparts) into an [`Xpub`] and the script type its version bytes imply, if any.
+pub fn decode_xpub(s: &str) -> Result<(Xpub, Option<ScriptType>), HashsatError> {
+    let invalid = |reason: &str| HashsatError::InvalidXpub(s.to_string(), reason.to_string());
+
+    let mut data = base58::decode_check(s).map_err(|_| invalid("invalid base58 encoding"))?;
+    if data.len() != 78 {
+        return Err(invalid("invalid length"));
+    }
+
+    let (_, network_kind, script_type) = VERSIONS
+        .iter()
+        .find(|(version, _, _)| data[..4] == version[..])
+        .ok_or_else(|| invalid("unknown version bytes"))?;
+
+    // Swap the SLIP-132 version bytes for the BIP32 ones, which is what `Xpub` understands.
+    data[..4].copy_from_slice(match network_kind {
+        NetworkKind::Main => &VERSION_XPUB,
+        NetworkKind::Test => &VERSION_TPUB,
+    });
+    let xpub = Xpub::decode(&data).map_err(|e| invalid(&e.to_string()))?;
+
+    Ok((xpub, *script_type))
+}
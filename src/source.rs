@@ -0,0 +1,154 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use core::fmt;
+use std::sync::Arc;
+
+use crate::{
+    brute_force::RoundRobinIter, error::HashsatError, partition::Partition, rules::Mangle,
+    rules::load_rules, types::Wallet, wordlist::Wordlist,
+};
+
+/// A generator of candidate passphrases, which the cracker tests in parallel.
+///
+/// Brute force, wordlists and masks are all candidate sources, and rules and partitions wrap
+/// them. Custom generators can be plugged in with
+/// [`CrackerBuilder::candidates`](crate::CrackerBuilder::candidates).
+///
+/// Sources that can tell their exact size up front get a preflight estimate of the time to
+/// exhaust them, and sources that can seek can be checkpointed to and restored from session
+/// files. Sessions of sources that can't seek are refused on restore.
+pub trait CandidateSource: Iterator<Item = String> + Send {
+    /// The exact number of candidates the source yields from its start, if known in advance.
+    fn keyspace(&self) -> Option<u128> {
+        None
+    }
+
+    /// The current position of the source, i.e. where the next candidate will be taken from.
+    fn position(&self) -> Vec<u128> {
+        Vec::new()
+    }
+
+    /// Move the source to a position previously returned by [`CandidateSource::position`], and
+    /// return whether it could, i.e. whether the source can seek and the position is one of its.
+    fn seek(&mut self, _position: &[u128]) -> bool {
+        false
+    }
+
+    /// Take the error that ended the source early, if any, so it isn't mistaken for the end of
+    /// the candidates.
+    fn take_error(&mut self) -> Option<HashsatError> {
+        None
+    }
+
+    /// How many entries the source skipped so far because they are not valid UTF-8, e.g. lines of
+    /// a wordlist, which are reported instead of being dropped silently.
+    fn skipped(&self) -> u128 {
+        0
+    }
+}
+
+impl<S: CandidateSource + ?Sized> CandidateSource for Box<S> {
+    fn keyspace(&self) -> Option<u128> {
+        (**self).keyspace()
+    }
+
+    fn position(&self) -> Vec<u128> {
+        (**self).position()
+    }
+
+    fn seek(&mut self, position: &[u128]) -> bool {
+        (**self).seek(position)
+    }
+
+    fn take_error(&mut self) -> Option<HashsatError> {
+        (**self).take_error()
+    }
+
+    fn skipped(&self) -> u128 {
+        (**self).skipped()
+    }
+}
+
+/// A custom candidate source, created anew for every run.
+#[derive(Clone)]
+pub(crate) struct Generator(Arc<dyn Fn() -> Box<dyn CandidateSource> + Send + Sync>);
+
+impl Generator {
+    pub(crate) fn new<S: CandidateSource + 'static>(
+        source: impl Fn() -> S + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(move || Box::new(source())))
+    }
+
+    /// Create a fresh source.
+    pub(crate) fn source(&self) -> Box<dyn CandidateSource> {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Generator")
+    }
+}
+
+/// Create the candidate passphrases of a wallet: a custom generator, a wordlist, a mask or the
+/// brute force of the alphabet scrambled with `seed`, each expanded by the rules and limited to
+/// the part to search, if any.
+pub(crate) fn candidates(
+    wallet: &Wallet,
+    seed: u64,
+) -> Result<Box<dyn CandidateSource>, HashsatError> {
+    let (min, max) = wallet.passphrase_length_range;
+    let alphabet: String = wallet.alphabet.chars().iter().collect();
+
+    // Either use the custom generator, stream candidates from a wordlist, generate them from a
+    // mask or use the Round-Robin iterator to join brute force subsets into a unified iterator.
+    let candidates: Box<dyn CandidateSource> =
+        match (&wallet.generator, &wallet.wordlist, &wallet.mask) {
+            (Some(generator), _, _) => generator.source(),
+            (_, Some(path), _) => Box::new(Wordlist::open(path)?),
+            (_, _, Some(mask)) => Box::new(mask.iter()),
+            _ => Box::new(RoundRobinIter::new(min, max, alphabet, seed)),
+        };
+    // Expand every candidate into its variants, if there are rules to apply.
+    let candidates: Box<dyn CandidateSource> = match &wallet.rules {
+        Some(path) => Box::new(Mangle::new(candidates, load_rules(path)?)),
+        None => candidates,
+    };
+    // Only keep this part's share of the candidates, if partitioned.
+    Ok(match wallet.part {
+        Some((k, n)) => Box::new(Partition::new(candidates, k - 1, n)),
+        None => candidates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{mask::Mask, rules::Rule};
+
+    #[test]
+    fn keyspace_hints_are_exact() {
+        let mask = Mask::new("?d?1", &[Some("abc".to_string()), None, None, None]).unwrap();
+        let rules = vec![Rule::from_str(":").unwrap(), Rule::from_str("u").unwrap()];
+        let brute_force = || RoundRobinIter::new(1, 3, "xyz".to_string(), 0);
+
+        let exact = |source: Box<dyn CandidateSource>| {
+            let keyspace = source.keyspace();
+            assert_eq!(keyspace, Some(source.count() as u128));
+        };
+        exact(Box::new(mask.iter()));
+        exact(Box::new(brute_force()));
+        exact(Box::new(Mangle::new(mask.iter(), rules.clone())));
+        for k in 0..4 {
+            exact(Box::new(Partition::new(
+                Mangle::new(brute_force(), rules.clone()),
+                k,
+                4,
+            )));
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{
+    io::{Write, stdout},
+    time::Duration,
+};
+
+use hashsat::{Backend, Estimate, Event, Jackpot, Measurement, Stats, Subscriber, Wallet};
+
+const COMMAS: [&str; 4] = ["", ".", "..", "..."];
+const SPINNERS: [char; 4] = ['\\', '|', '/', '–'];
+
+/// Format a [`Duration`] in the `hh:mm:ss` format, with days and years for longer durations, and
+/// [`Duration::MAX`] as longer than the years it holds.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    if duration == Duration::MAX {
+        return format!(
+            "longer than {} years",
+            format_number(duration.as_secs() / (365 * 86400))
+        );
+    }
+    let total_seconds = duration.as_secs();
+    let years = total_seconds / (365 * 86400);
+    let days = (total_seconds % (365 * 86400)) / 86400;
+    let hours = (total_seconds % 86400) / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let seconds = total_seconds % 60;
+
+    if years > 0 {
+        format!("{}y {days}d {hours}h", format_number(years))
+    } else if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// Format a number with commas as the thousands separator.
+pub(crate) fn format_number(n: impl ToString) -> String {
+    n.to_string()
+        .chars()
+        .rev()
+        .collect::<Vec<_>>()
+        .chunks(3)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(",")
+        .chars()
+        .rev()
+        .collect()
+}
+
+/// Print cracking parameters.
+pub(crate) fn print_cracking_params(wallet: &Wallet) {
+    println!("cracking");
+    println!(" {}", wallet.mnemonic);
+    match (
+        wallet.has_custom_candidates(),
+        &wallet.wordlist,
+        &wallet.mask,
+    ) {
+        (true, _, _) => {
+            println!("using candidates");
+            println!(" custom");
+        }
+        (_, Some(wordlist), _) => {
+            println!("using wordlist");
+            println!(" {}", wordlist.display());
+        }
+        (_, _, Some(mask)) => {
+            println!("using mask");
+            println!(" {mask} ({} candidates)", format_number(mask.keyspace()));
+        }
+        _ => {
+            println!("using alphabet");
+            println!(
+                " {} ({})",
+                wallet.alphabet,
+                wallet.alphabet.chars().iter().collect::<String>()
+            );
+        }
+    }
+    if let Some(target_fingerprint) = &wallet.target_fingerprint {
+        println!("with target fingerprint");
+        println!(" {target_fingerprint}");
+    }
+    match &wallet.target_xpub {
+        Some(target_xpub) => {
+            println!("with target xpub");
+            println!(" {target_xpub}");
+        }
+        None if wallet.target_addresses.is_empty() => {}
+        None => {
+            println!("with target addresses");
+            for target_address in &wallet.target_addresses {
+                println!(" {target_address}");
+            }
+        }
+    }
+    if wallet.has_custom_matcher() {
+        println!("with matcher");
+        println!(" custom");
+    }
+    println!("on network");
+    println!(" {}", wallet.network);
+    if wallet.has_child_targets() {
+        println!("with descriptors");
+        for descriptor in &wallet.descriptors {
+            println!(" {descriptor} ({})", descriptor.script_type.address_type());
+        }
+    }
+    if !wallet.target_addresses.is_empty() {
+        println!("with search width of");
+        println!(" {} addresses per keychain", wallet.search_width);
+    }
+    if let Some(rules) = &wallet.rules {
+        println!("mangled with rules");
+        println!(" {}", rules.display());
+    }
+    if wallet.is_brute_force() {
+        println!("and passphrase length range of");
+        println!(
+            " ({},{})",
+            wallet.passphrase_length_range.0, wallet.passphrase_length_range.1
+        );
+    }
+    if let Some((k, n)) = wallet.part {
+        println!("searching part");
+        println!(" {k} of {n}");
+    }
+    println!("using");
+    println!(" {} threads", rayon::current_num_threads());
+    println!(" {} seed derivation", Backend::detect());
+}
+
+/// Print the preflight estimate of how long it takes to exhaust the candidate space.
+pub(crate) fn print_estimate(estimate: &Estimate) {
+    println!("with a candidate space of");
+    match estimate.candidates {
+        Some(candidates) if estimate.tried > 0 => println!(
+            " {} candidates ({} left)",
+            format_number(candidates),
+            format_number(candidates.saturating_sub(estimate.tried as u128))
+        ),
+        Some(candidates) => println!(" {} candidates", format_number(candidates)),
+        None => println!(" unknown candidates (reading from stdin)"),
+    }
+    println!("at an estimated");
+    println!(
+        " {} wallets per second",
+        format_number(estimate.rate as u64)
+    );
+    match (estimate.worst_case(), estimate.expected()) {
+        (Some(Duration::MAX), _) => {
+            println!("exhausting it in");
+            println!(" {}", format_duration(Duration::MAX));
+        }
+        (Some(worst_case), Some(expected)) => {
+            println!("exhausting it in");
+            println!(
+                " {} ({} expected)",
+                format_duration(worst_case),
+                format_duration(expected)
+            );
+        }
+        _ => {}
+    }
+    println!();
+}
+
+/// Print the size of the candidate space before cracking, when it can be counted up front. Wordlist
+/// files are only counted with `--estimate`, as that reads them through.
+pub(crate) fn print_keyspace(wallet: &Wallet, candidates: Option<u128>) {
+    println!("with a candidate space of");
+    match (candidates, &wallet.wordlist) {
+        (Some(candidates), _) => println!(" {} candidates", format_number(candidates)),
+        (None, Some(path)) if path.as_os_str() != "-" => {
+            println!(" unknown candidates (count them with --estimate)")
+        }
+        (None, _) => println!(" unknown candidates (reading from stdin)"),
+    }
+    println!();
+}
+
+/// Print the configuration of a cracked wallet and its findings.
+pub(crate) fn print_jackpot(wallet: &Wallet, jackpot: &Jackpot) {
+    println!("{wallet}{jackpot}");
+    if wallet.is_fingerprint_only() {
+        println!(
+            "warning: this passphrase was only matched against a 32-bit fingerprint, which can collide. Confirm it on your wallet, or crack again with a target address to rule out a collision.\n"
+        );
+    }
+}
+
+/// Print the header of the benchmark table, with a column for each thread count.
+pub(crate) fn print_bench_header(backend: Backend, duration: Duration, threads: &[usize]) {
+    println!(
+        "\nbenchmarking {backend} seed derivation for {}s per measurement, in wallets per second\n",
+        duration.as_secs_f64()
+    );
+    print!("{:<30}", "stage");
+    for &threads in threads {
+        print!(
+            "{:>16}",
+            match threads {
+                1 => "1 thread".to_string(),
+                threads => format!("{threads} threads"),
+            }
+        );
+    }
+    println!();
+}
+
+/// Print a measurement in the `column` of the benchmark table, starting the row of its stage on the
+/// first column and ending it on the last one.
+pub(crate) fn print_measurement(measurement: &Measurement, column: usize, columns: usize) {
+    let stage = measurement.stage;
+    if column == 0 {
+        print!(
+            "{:<30}",
+            match stage.script_type() {
+                Some(script_type) => format!("{} ({})", stage.name(), script_type.address_type()),
+                None => stage.name().to_string(),
+            }
+        );
+    }
+    print!("{:>16}", format_number(measurement.rate as u64));
+    if column + 1 == columns {
+        println!();
+    }
+    stdout().flush().unwrap_or(());
+}
+
+/// Renders the events of a cracking run on the terminal, with a spinner showing progress.
+#[derive(Debug, Default)]
+pub(crate) struct Renderer {
+    comma_idx: usize,
+    spinner_idx: usize,
+}
+
+impl Renderer {
+    /// Print the spinner line with the latest candidate and statistics.
+    fn spin(&self, candidate: &str, stats: &Stats) {
+        print!(
+            "\r{} cracking sats : {} ({} wallets in {}){:<3}",
+            SPINNERS[self.spinner_idx],
+            candidate,
+            format_number(stats.tries),
+            format_duration(stats.elapsed),
+            COMMAS[self.comma_idx],
+        );
+        stdout().flush().unwrap_or(());
+    }
+}
+
+impl Subscriber for Renderer {
+    fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::Started { .. } => {
+                // Hide the cursor.
+                print!("\x1b[?25l");
+                stdout().flush().unwrap_or(());
+            }
+            Event::Progress { stats, candidate } => {
+                self.spin(candidate, stats);
+                self.comma_idx = (self.comma_idx + 1) % COMMAS.len();
+                self.spinner_idx = (self.spinner_idx + 1) % SPINNERS.len();
+            }
+            Event::Skipped { skipped } => {
+                eprintln!(
+                    "\nwarn: skipped {} candidates that are not valid UTF-8",
+                    format_number(*skipped)
+                );
+            }
+            Event::Checkpointed { .. } => {}
+            Event::CheckpointFailed { error, .. } => {
+                eprintln!("\nwarn: failed to checkpoint session: {error}");
+            }
+            Event::Found { jackpot, stats } => {
+                (self.comma_idx, self.spinner_idx) = (0, 0);
+                self.spin(&jackpot.passphrase, stats);
+                // Unhide the cursor.
+                print!("\x1b[?25h");
+
+                println!("\n\nJACKPOT!");
+                println!(
+                    "hashsat found your lost sats in {} and {} tries ({} wallets per second)\n",
+                    format_duration(stats.elapsed),
+                    format_number(stats.tries),
+                    format_number(stats.rate as u64)
+                );
+            }
+            Event::Exhausted { .. } => {
+                print!("\x1b[?25h");
+                println!("\nSearch space depleted without finding passphrase");
+            }
+            Event::Cancelled { .. } => {
+                print!("\x1b[?25h");
+                println!("\nCancelled without finding passphrase");
+            }
+            Event::Failed { .. } => {
+                print!("\x1b[?25h");
+                stdout().flush().unwrap_or(());
+            }
+        }
+    }
+}
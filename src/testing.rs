@@ -0,0 +1,52 @@
+//! hashsat: a bitcoin passphrase cracker
+//!
+//! Test fixtures, shared by the tests of the library and of the binary behind the `testing`
+//! feature.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use bip39::Mnemonic;
+
+use crate::{Cracker, CrackerBuilder, Wallet};
+
+/// The mnemonic of the test wallets, from the BIP39 test vectors.
+pub const TEST_MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+/// A builder for the wallet of [`TEST_MNEMONIC`], without any target yet.
+pub fn builder() -> CrackerBuilder {
+    Cracker::builder(Mnemonic::from_str(TEST_MNEMONIC).unwrap())
+}
+
+/// Build the wallet configured by `builder`.
+pub fn wallet(builder: CrackerBuilder) -> Wallet {
+    builder.build().unwrap().wallet().clone()
+}
+
+/// A temporary file for tests, removed on drop.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Write `contents` to a temporary file. `name` must be unique across the tests of the crate,
+    /// as they run in parallel.
+    pub fn new(name: &str, contents: impl AsRef<[u8]>) -> Self {
+        let path = std::env::temp_dir().join(format!("hashsat-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        Self(path)
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
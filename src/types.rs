@@ -1,70 +1,223 @@
 //! hashsat: a bitcoin passphrase cracker
 
 use core::fmt;
+use std::{path::PathBuf, time::Duration};
 
 use bip39::Mnemonic;
 use bitcoin::{
     Address, Network,
-    bip32::{DerivationPath, Xpriv, Xpub},
+    bip32::{DerivationPath, Fingerprint, Xpriv, Xpub},
 };
 
-/// Abstract representation of a lost wallet.
+use crate::{
+    alphabet::Alphabet,
+    descriptor::Descriptor,
+    error::HashsatError,
+    mask::Mask,
+    matcher::{
+        AddressMatcher, All, CandidateWallet, CustomMatcher, FingerprintMatcher, Matcher,
+        XpubMatcher,
+    },
+    source::Generator,
+};
+
+/// Abstract representation of a lost wallet, and of how to crack its passphrase.
+///
+/// Built and validated by a [`CrackerBuilder`](crate::CrackerBuilder).
 #[derive(Clone, Debug)]
-pub(crate) struct Wallet {
+pub struct Wallet {
     /// The BIP39-compliant mnemonic.
-    pub(crate) mnemonic: Mnemonic,
+    pub mnemonic: Mnemonic,
     /// The alphabet used to search for the passphrase.
-    pub(crate) alphabet: String,
-    /// The target address where it is known coins are locked.
-    pub(crate) target_address: Address,
-    /// The derivation path used on the search.
-    /// The deafault derivation path for the address type will be used if this is left empty.
-    pub(crate) derivation_path: DerivationPath,
+    pub alphabet: Alphabet,
+    /// The wordlist to read candidate passphrases from, instead of brute forcing the alphabet.
+    /// A path of `-` reads from stdin.
+    pub wordlist: Option<PathBuf>,
+    /// The mask to generate candidate passphrases from, instead of brute forcing the alphabet.
+    pub mask: Option<Mask>,
+    /// The custom generator of candidate passphrases, used instead of all of the above.
+    pub(crate) generator: Option<Generator>,
+    /// The rule file used to mangle every candidate passphrase into variants.
+    pub rules: Option<PathBuf>,
+    /// The target addresses where it is known coins are locked.
+    pub target_addresses: Vec<Address>,
+    /// The target account extended public key, matched instead of `target_addresses`.
+    pub target_xpub: Option<Xpub>,
+    /// The target master key fingerprint, checked before any child key is derived.
+    pub target_fingerprint: Option<Fingerprint>,
+    /// A custom matcher, which must match on top of the targets above.
+    pub(crate) custom_matcher: Option<CustomMatcher>,
+    /// The descriptor templates to derive addresses from, each with its script type, account
+    /// derivation path and keychains. The standard descriptor for the address type of each target
+    /// will be used if none is provided.
+    pub descriptors: Vec<Descriptor>,
     /// The maximum search width for a parent key on the BIP32 HD tree.
-    pub(crate) search_width: usize,
+    pub search_width: usize,
     /// The maximum passphrase length to search.
-    pub(crate) passphrase_length_range: (usize, usize),
+    pub passphrase_length_range: (usize, usize),
     /// The network to be searched.
-    pub(crate) network: Network,
+    pub network: Network,
+    /// The part `k` out of `N` of the candidate space to search, if partitioned.
+    pub part: Option<(usize, usize)>,
+    /// The seed used to scramble the alphabet. Runs with the same seed walk the same path.
+    pub seed: Option<u64>,
+    /// The session file where progress is checkpointed to.
+    pub session: Option<PathBuf>,
+    /// Whether to restore progress from the session file.
+    pub restore: bool,
+    /// Whether to overwrite an existing session file when not restoring it.
+    pub overwrite_session: bool,
+    /// How often progress is checkpointed to the session file.
+    pub checkpoint_interval: Duration,
+}
+
+/// The findings of a successful crack.
+#[derive(Clone, Debug)]
+pub struct Jackpot {
     /// The cracked passphrase.
-    pub(crate) passphrase: Option<String>,
-    /// The cracked extended public key.
-    pub(crate) xpub: Option<Xpub>,
-    /// The cracked extended private key.
-    pub(crate) xpriv: Option<Xpriv>,
+    pub passphrase: String,
+    /// The cracked master extended public key.
+    pub xpub: Xpub,
+    /// The cracked master extended private key.
+    pub xpriv: Xpriv,
+    /// The target address that was matched, if matching against addresses.
+    pub address: Option<Address>,
+    /// The full derivation path of the matched address, or of the matched account.
+    pub derivation_path: DerivationPath,
+}
+
+impl Wallet {
+    /// Whether there are target addresses or a target xpub to match against. If not, only the
+    /// target fingerprint is matched.
+    pub fn has_child_targets(&self) -> bool {
+        !self.target_addresses.is_empty() || self.target_xpub.is_some()
+    }
+
+    /// Whether only the target fingerprint is matched against, which can collide.
+    pub fn is_fingerprint_only(&self) -> bool {
+        !self.has_child_targets() && !self.has_custom_matcher()
+    }
+
+    /// Whether a custom matcher must match on top of the targets.
+    pub fn has_custom_matcher(&self) -> bool {
+        self.custom_matcher.is_some()
+    }
+
+    /// Whether candidate passphrases are taken from a custom generator.
+    pub fn has_custom_candidates(&self) -> bool {
+        self.generator.is_some()
+    }
+
+    /// The matcher of the wallet: the target fingerprint, then the target xpub or addresses, then
+    /// the custom matcher, each only if set. The fingerprint goes first since it is the cheapest
+    /// to check, and confirms nothing on its own.
+    pub(crate) fn matcher(&self) -> Result<All, HashsatError> {
+        let mut matchers: Vec<Box<dyn Matcher>> = Vec::new();
+        if let Some(target_fingerprint) = self.target_fingerprint {
+            matchers.push(Box::new(FingerprintMatcher(target_fingerprint)));
+        }
+        match self.target_xpub {
+            Some(target_xpub) => {
+                matchers.push(Box::new(XpubMatcher::new(target_xpub, &self.descriptors)));
+            }
+            None if !self.target_addresses.is_empty() => {
+                matchers.push(Box::new(AddressMatcher::new(
+                    &self.target_addresses,
+                    self.descriptors.clone(),
+                    self.search_width,
+                    self.network,
+                )?));
+            }
+            None => {}
+        }
+        if let Some(custom_matcher) = &self.custom_matcher {
+            let custom_matcher = custom_matcher.0.clone();
+            matchers.push(Box::new(move |wallet: &CandidateWallet| {
+                custom_matcher.matches(wallet)
+            }));
+        }
+
+        Ok(All(matchers))
+    }
+
+    /// Whether candidate passphrases are brute forced from `alphabet`, as opposed to being read
+    /// from a wordlist or generated from a mask or a custom generator.
+    pub fn is_brute_force(&self) -> bool {
+        self.generator.is_none() && self.wordlist.is_none() && self.mask.is_none()
+    }
 }
 
 impl fmt::Display for Wallet {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "mnemonic: {}", self.mnemonic)?;
-        writeln!(f, "alphabet: {}", self.alphabet)?;
-        writeln!(f, "target address: {}", self.target_address)?;
-        writeln!(f, "derivation path: {}", self.derivation_path)?;
+        match (&self.generator, &self.wordlist, &self.mask) {
+            (Some(_), _, _) => writeln!(f, "candidates: custom")?,
+            (_, Some(wordlist), _) => writeln!(f, "wordlist: {}", wordlist.display())?,
+            (_, _, Some(mask)) => writeln!(f, "mask: {mask}")?,
+            _ => writeln!(f, "alphabet: {}", self.alphabet)?,
+        }
+        if let Some(rules) = &self.rules {
+            writeln!(f, "rules: {}", rules.display())?;
+        }
+        if let Some(target_fingerprint) = &self.target_fingerprint {
+            writeln!(f, "target fingerprint: {target_fingerprint}")?;
+        }
+        match &self.target_xpub {
+            Some(target_xpub) => writeln!(f, "target xpub: {target_xpub}")?,
+            None if self.target_addresses.is_empty() => {}
+            None => writeln!(
+                f,
+                "target addresses: {}",
+                self.target_addresses
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?,
+        }
+        if self.has_child_targets() {
+            writeln!(
+                f,
+                "descriptors: {}",
+                self.descriptors
+                    .iter()
+                    .map(|descriptor| descriptor.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        if self.custom_matcher.is_some() {
+            writeln!(f, "matcher: custom")?;
+        }
         writeln!(f, "search width: {}", self.search_width)?;
-        writeln!(
-            f,
-            "passphrase length range: ({},{})",
-            self.passphrase_length_range.0, self.passphrase_length_range.1
-        )?;
+        if self.is_brute_force() {
+            writeln!(
+                f,
+                "passphrase length range: ({},{})",
+                self.passphrase_length_range.0, self.passphrase_length_range.1
+            )?;
+        }
         writeln!(f, "network: {}", self.network)?;
-        writeln!(
-            f,
-            "passphrase: {}",
-            self.passphrase.as_deref().unwrap_or("not found yet")
-        )?;
-        writeln!(
-            f,
-            "xpub: {}",
-            self.xpub
-                .as_ref()
-                .map_or("not found yet".to_string(), |x| x.to_string())
-        )?;
-        writeln!(
-            f,
-            "xpriv: {}",
-            self.xpriv
-                .as_ref()
-                .map_or("not found yet".to_string(), |x| x.to_string())
-        )
+        if let Some((k, n)) = self.part {
+            writeln!(f, "part: {k}/{n}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Jackpot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "passphrase: {}", self.passphrase)?;
+        writeln!(f, "xpub: {}", self.xpub)?;
+        writeln!(f, "xpriv: {}", self.xpriv)?;
+        if let Some(address) = &self.address {
+            writeln!(f, "matched address: {address}")?;
+        }
+        if !self.derivation_path.is_master() {
+            writeln!(f, "matched derivation path: m/{}", self.derivation_path)?;
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,200 @@
+//! hashsat: a bitcoin passphrase cracker
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{error::HashsatError, source::CandidateSource};
+
+/// Streaming iterator over the candidate passphrases of a wordlist, one per line.
+///
+/// Lines are read lazily from a buffered reader, so wordlists of any size can be used without
+/// loading them into memory. Trailing `\n` and `\r\n` line endings are stripped and lines that
+/// are not valid UTF-8 are skipped, since they can't be BIP39 passphrases, and counted by
+/// [`CandidateSource::skipped`]. Iteration stops at the
+/// end of the input or on the first I/O error, which is then reported by
+/// [`CandidateSource::take_error`] instead of passing for the end of the wordlist.
+pub(crate) struct Wordlist {
+    /// The path of the wordlist file, if not read from stdin or another reader.
+    path: Option<PathBuf>,
+    /// The underlying buffered reader.
+    reader: Box<dyn BufRead + Send>,
+    /// Reusable line buffer.
+    buf: Vec<u8>,
+    /// How many lines were read so far.
+    lines: u128,
+    /// How many of them were skipped, as they are not valid UTF-8.
+    skipped: u128,
+    /// The I/O error that stopped the iteration, if any.
+    error: Option<io::Error>,
+    /// The number of lines of the wordlist file, counted once on demand.
+    keyspace: OnceLock<Option<u128>>,
+}
+
+impl Wordlist {
+    /// Open the wordlist at `path`. A path of `-` reads from stdin.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        if path == Path::new("-") {
+            return Ok(Self::new(Box::new(BufReader::new(io::stdin()))));
+        }
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..Self::new(Box::new(BufReader::new(File::open(path)?)))
+        })
+    }
+
+    /// Create a wordlist from any buffered reader.
+    pub(crate) fn new(reader: Box<dyn BufRead + Send>) -> Self {
+        Self {
+            path: None,
+            reader,
+            buf: Vec::new(),
+            lines: 0,
+            skipped: 0,
+            error: None,
+            keyspace: OnceLock::new(),
+        }
+    }
+}
+
+impl Iterator for Wordlist {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+                Ok(_) => {
+                    self.lines += 1;
+                    if self.buf.last() == Some(&b'\n') {
+                        self.buf.pop();
+                        if self.buf.last() == Some(&b'\r') {
+                            self.buf.pop();
+                        }
+                    }
+                    match std::str::from_utf8(&self.buf) {
+                        Ok(line) => return Some(line.to_string()),
+                        Err(_) => self.skipped += 1,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl CandidateSource for Wordlist {
+    /// Wordlist files are counted by reading them through once more, the first time they are
+    /// asked for their size only. Other readers are streamed, so their size is unknown.
+    fn keyspace(&self) -> Option<u128> {
+        *self.keyspace.get_or_init(|| {
+            let path = self.path.as_deref()?;
+            Some(Wordlist::open(path).ok()?.count() as u128)
+        })
+    }
+
+    fn position(&self) -> Vec<u128> {
+        vec![self.lines]
+    }
+
+    /// Skip lines up to `position`. Wordlists are streamed, so they can only seek forward.
+    fn seek(&mut self, position: &[u128]) -> bool {
+        let [lines] = position else {
+            return false;
+        };
+        while self.lines < *lines {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => break,
+                Err(e) => {
+                    self.error = Some(e);
+                    break;
+                }
+                Ok(_) => self.lines += 1,
+            }
+        }
+        true
+    }
+
+    fn take_error(&mut self) -> Option<HashsatError> {
+        self.error.take().map(HashsatError::from)
+    }
+
+    fn skipped(&self) -> u128 {
+        self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Cursor, Read},
+    };
+
+    use super::*;
+    use crate::testing::TempFile;
+
+    /// A reader that fails after its data.
+    struct Failing(Cursor<Vec<u8>>);
+
+    impl Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::Error::other("disk on fire")),
+                n => Ok(n),
+            }
+        }
+    }
+
+    #[test]
+    fn lines() {
+        let wordlist = |bytes: &[u8]| Wordlist::new(Box::new(Cursor::new(bytes.to_vec())));
+
+        // CRLF and LF line endings, empty lines and a missing final line ending.
+        let words: Vec<String> = wordlist(b"foo\r\nbar\n\n\r\nbaz").collect();
+        assert_eq!(words, ["foo", "bar", "", "", "baz"]);
+
+        // Lines that are not valid UTF-8 are skipped, without stopping the iteration, but still
+        // count towards the position, and are counted to be reported.
+        let mut words = wordlist(b"foo\n\xff\xfe\nbar\n\xc3\n");
+        assert_eq!(words.by_ref().collect::<Vec<_>>(), ["foo", "bar"]);
+        assert_eq!(words.position(), [4]);
+        assert_eq!(words.skipped(), 2);
+        assert!(words.take_error().is_none());
+
+        // I/O errors stop the iteration, and are reported.
+        let mut words = Wordlist::new(Box::new(BufReader::new(Failing(Cursor::new(
+            b"foo\nbar".to_vec(),
+        )))));
+        assert_eq!(words.next().as_deref(), Some("foo"));
+        assert_eq!(words.next(), None);
+        assert!(matches!(words.take_error(), Some(HashsatError::IOError(_))));
+    }
+
+    #[test]
+    fn keyspace() {
+        let file = TempFile::new("wordlist-keyspace", b"foo\n\xff\nbar\n");
+        let words = Wordlist::open(file.path()).unwrap();
+        assert_eq!(words.keyspace(), Some(2));
+
+        // The file is only read through once.
+        fs::remove_file(file.path()).unwrap();
+        assert_eq!(words.keyspace(), Some(2));
+
+        // Other readers are streamed, so they can't be counted.
+        let stream = Wordlist::new(Box::new(Cursor::new(b"foo\n".to_vec())));
+        assert_eq!(stream.keyspace(), None);
+    }
+}